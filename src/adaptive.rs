@@ -0,0 +1,156 @@
+use crate::color::{Color, luminance};
+use crate::error::{Error, Result};
+use std::io::{self, Write};
+
+/// Settings for variance-driven adaptive sampling.
+///
+/// Every pixel takes at least `min_samples`, then keeps sampling in rounds of
+/// `batch_size` until the 95% confidence interval of its luminance is within
+/// `max_relative_error` of the mean, or `max_samples` is reached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    pub min_samples: u32,
+    pub max_samples: u32,
+    pub batch_size: u32,
+    pub max_relative_error: f64
+}
+
+impl AdaptiveSampling {
+    pub fn new(min_samples: u32, max_samples: u32, max_relative_error: f64) -> AdaptiveSampling {
+        Self::try_new(min_samples, max_samples, max_relative_error).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(min_samples: u32, max_samples: u32, max_relative_error: f64) -> Result<AdaptiveSampling> {
+        if min_samples == 0 { return Err(Error::invalid_value("Minimum samples", "must be at least one")) };
+        if max_samples < min_samples { return Err(Error::invalid_value("Maximum samples", "cannot be less than minimum samples")) };
+        if !(max_relative_error.is_finite() && max_relative_error > 0.0) {
+            return Err(Error::invalid_value("Maximum relative error", "must be positive"));
+        };
+        Ok(AdaptiveSampling {
+            min_samples,
+            max_samples,
+            batch_size: min_samples,
+            max_relative_error
+        })
+    }
+}
+
+/// Running mean and variance of a pixel's samples, using Welford's algorithm on luminance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelStats {
+    pub samples: u32,
+    sum: Color,
+    mean_luminance: f64,
+    m2: f64
+}
+
+impl PixelStats {
+    pub const fn new() -> PixelStats {
+        PixelStats {
+            samples: 0,
+            sum: Color::zeroes(),
+            mean_luminance: 0.0,
+            m2: 0.0
+        }
+    }
+
+    pub fn add(&mut self, sample: Color) {
+        self.samples += 1;
+        self.sum += sample;
+
+        let value = luminance(sample);
+        let delta = value - self.mean_luminance;
+        self.mean_luminance += delta / self.samples as f64;
+        self.m2 += delta * (value - self.mean_luminance);
+    }
+
+    pub fn mean(&self) -> Color {
+        if self.samples == 0 { Color::zeroes() } else { self.sum / self.samples as f64 }
+    }
+
+    pub fn variance(&self) -> f64 {
+        if self.samples < 2 { 0.0 } else { self.m2 / (self.samples - 1) as f64 }
+    }
+
+    /// Whether the pixel needs no further samples under `settings`.
+    pub fn converged(&self, settings: &AdaptiveSampling) -> bool {
+        if self.samples >= settings.max_samples { return true };
+        if self.samples < settings.min_samples { return false };
+
+        let confidence_interval = 1.96 * (self.variance() / self.samples as f64).sqrt();
+        confidence_interval <= settings.max_relative_error * f64::max(self.mean_luminance, 1e-3)
+    }
+}
+
+impl Default for PixelStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes a P3 heatmap of samples taken per pixel, from blue (none) to red (`max_samples`).
+pub fn write_heatmap(stream: &mut dyn Write, width: usize, height: usize, sample_counts: &[u32], max_samples: u32) -> io::Result<()> {
+    write!(stream, "P3\n{} {}\n255\n", width, height)?;
+    for &count in sample_counts {
+        let t = if max_samples == 0 { 0.0 } else { f64::min(count as f64 / max_samples as f64, 1.0) };
+        let r = (255.999 * t) as i32;
+        let g = (255.999 * (1.0 - (2.0 * t - 1.0).abs())) as i32;
+        let b = (255.999 * (1.0 - t)) as i32;
+        writeln!(stream, "{} {} {}", r, g, b)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let mut stats = PixelStats::new();
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            stats.add(Color::new(value, value, value));
+        }
+        assert_eq!(stats.samples, 4);
+        assert_eq!(stats.mean(), Color::new(2.5, 2.5, 2.5));
+        assert!((stats.variance() - 5.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert!(AdaptiveSampling::try_new(0, 64, 0.05).is_err());
+        assert!(AdaptiveSampling::try_new(16, 8, 0.05).is_err());
+        assert!(AdaptiveSampling::try_new(4, 64, -0.05).is_err());
+        assert!(AdaptiveSampling::try_new(4, 64, f64::NAN).is_err());
+        assert!(AdaptiveSampling::try_new(4, 64, f64::INFINITY).is_err());
+        assert!(AdaptiveSampling::try_new(4, 64, 0.05).is_ok());
+    }
+
+    #[test]
+    fn converged() {
+        let settings = AdaptiveSampling::new(4, 64, 0.05);
+        let mut flat = PixelStats::new();
+        for _ in 0..4 {
+            flat.add(Color::new(0.5, 0.7, 1.0));
+        }
+        assert!(flat.converged(&settings));
+
+        let mut noisy = PixelStats::new();
+        for i in 0..4 {
+            noisy.add(if i % 2 == 0 { Color::ones() } else { Color::zeroes() });
+        }
+        assert!(!noisy.converged(&settings));
+
+        for _ in 4..64 {
+            noisy.add(Color::zeroes());
+        }
+        assert!(noisy.converged(&settings));
+    }
+
+    #[test]
+    fn heatmap() {
+        let mut out = Vec::new();
+        write_heatmap(&mut out, 2, 1, &[0, 10], 10).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "P3\n2 1\n255\n0 0 255\n255 0 0\n");
+    }
+}
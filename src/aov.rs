@@ -0,0 +1,187 @@
+use crate::color::Color;
+use crate::film::Film;
+use crate::vec3::Vec3;
+
+/// Arbitrary output variables of one camera sample, taken from its first hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AovSample {
+    pub albedo: Color,
+    pub normal: Vec3,
+    /// Distance from the ray origin to the hit, or zero for a miss.
+    pub depth: f64,
+    /// Identifier of the object hit, or zero for a miss.
+    pub object_id: u32,
+    /// Light group of the sky seen directly, so zero for a hit.
+    pub background: Color,
+    /// Light group of the sky lighting the scene, so zero for a miss.
+    pub sky: Color
+}
+
+impl AovSample {
+    /// A sample that escaped to the background, whose color counts as its albedo.
+    pub fn miss(background: Color) -> AovSample {
+        AovSample {
+            albedo: background,
+            normal: Vec3::zeroes(),
+            depth: 0.0,
+            object_id: 0,
+            background,
+            sky: Color::zeroes()
+        }
+    }
+}
+
+/// Filter-weighted sums of AOV samples for every pixel of a film. Object IDs cannot be averaged,
+/// so each pixel keeps the ID of the sample with the largest filter weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AovBuffer {
+    albedo: Vec<Color>,
+    normal: Vec<Vec3>,
+    depth: Vec<f64>,
+    object_id: Vec<u32>,
+    object_weight: Vec<f64>,
+    background: Vec<Color>,
+    sky: Vec<Color>
+}
+
+impl AovBuffer {
+    pub fn new(len: usize) -> AovBuffer {
+        AovBuffer {
+            albedo: vec![Color::zeroes(); len],
+            normal: vec![Vec3::zeroes(); len],
+            depth: vec![0.0; len],
+            object_id: vec![0; len],
+            object_weight: vec![0.0; len],
+            background: vec![Color::zeroes(); len],
+            sky: vec![Color::zeroes(); len]
+        }
+    }
+
+    pub fn add(&mut self, index: usize, weight: f64, sample: &AovSample) {
+        self.albedo[index] += weight * sample.albedo;
+        self.normal[index] += weight * sample.normal;
+        self.depth[index] += weight * sample.depth;
+        self.background[index] += weight * sample.background;
+        self.sky[index] += weight * sample.sky;
+        if weight > self.object_weight[index] {
+            self.object_id[index] = sample.object_id;
+            self.object_weight[index] = weight;
+        }
+    }
+
+    /// Adds pixel `src` of `other` into pixel `dst` of this buffer.
+    pub fn merge_pixel(&mut self, dst: usize, other: &AovBuffer, src: usize) {
+        self.albedo[dst] += other.albedo[src];
+        self.normal[dst] += other.normal[src];
+        self.depth[dst] += other.depth[src];
+        self.background[dst] += other.background[src];
+        self.sky[dst] += other.sky[src];
+        if other.object_weight[src] > self.object_weight[dst] {
+            self.object_id[dst] = other.object_id[src];
+            self.object_weight[dst] = other.object_weight[src];
+        }
+    }
+
+    /// Normalizes the sums by the beauty pass's filter `weights`.
+    pub fn resolve(&self, width: usize, height: usize, weights: &[f64]) -> Aovs {
+        let scale = |weight: f64| if weight.abs() > 1e-12 { 1.0 / weight } else { 0.0 };
+        Aovs {
+            width,
+            height,
+            albedo: self.albedo.iter().zip(weights).map(|(&sum, &weight)| scale(weight) * sum).collect(),
+            normal: self.normal.iter().zip(weights).map(|(&sum, &weight)| scale(weight) * sum).collect(),
+            depth: self.depth.iter().zip(weights).map(|(&sum, &weight)| scale(weight) * sum).collect(),
+            object_id: self.object_id.clone(),
+            background: self.background.iter().zip(weights).map(|(&sum, &weight)| scale(weight) * sum).collect(),
+            sky: self.sky.iter().zip(weights).map(|(&sum, &weight)| scale(weight) * sum).collect()
+        }
+    }
+}
+
+/// Resolved AOV layers of a render, in scanline order. The `background` and `sky` light groups
+/// add up to the beauty pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aovs {
+    pub width: usize,
+    pub height: usize,
+    pub albedo: Vec<Color>,
+    pub normal: Vec<Vec3>,
+    pub depth: Vec<f64>,
+    pub object_id: Vec<u32>,
+    pub background: Vec<Color>,
+    pub sky: Vec<Color>
+}
+
+impl Aovs {
+    pub fn albedo_film(&self) -> Film {
+        Film::from_colors(self.width, self.height, self.albedo.clone())
+    }
+
+    /// Normals remapped from [-1, 1] to [0, 1] for viewing.
+    pub fn normal_film(&self) -> Film {
+        let colors = self.normal.iter().map(|&normal| 0.5 * (normal + Vec3::ones())).collect();
+        Film::from_colors(self.width, self.height, colors)
+    }
+
+    /// Depth as gray levels, divided by the farthest depth when `normalize` is set.
+    pub fn depth_film(&self, normalize: bool) -> Film {
+        let max_depth = self.depth.iter().copied().fold(0.0, f64::max);
+        let scale = if normalize && max_depth > 0.0 { 1.0 / max_depth } else { 1.0 };
+        let colors = self.depth.iter().map(|&depth| scale * depth * Color::ones()).collect();
+        Film::from_colors(self.width, self.height, colors)
+    }
+
+    /// Object IDs as distinct pseudo-random colors, with the background black.
+    pub fn object_id_film(&self) -> Film {
+        let colors = self.object_id.iter().map(|&id| id_color(id)).collect();
+        Film::from_colors(self.width, self.height, colors)
+    }
+
+    pub fn background_film(&self) -> Film {
+        Film::from_colors(self.width, self.height, self.background.clone())
+    }
+
+    pub fn sky_film(&self) -> Film {
+        Film::from_colors(self.width, self.height, self.sky.clone())
+    }
+}
+
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return Color::zeroes();
+    }
+    let hash = id.wrapping_mul(0x9e37_79b9).rotate_left(13).wrapping_mul(0x85eb_ca6b);
+    let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f64 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_average() {
+        let mut buffer = AovBuffer::new(1);
+        let near = AovSample { albedo: Color::ones(), normal: Vec3::new(0.0, 1.0, 0.0), depth: 2.0, object_id: 3, background: Color::zeroes(), sky: Color::ones() };
+        buffer.add(0, 3.0, &near);
+        buffer.add(0, 1.0, &AovSample::miss(Color::new(0.0, 0.0, 4.0)));
+
+        let aovs = buffer.resolve(1, 1, &[4.0]);
+        assert_eq!(aovs.albedo[0], Color::new(0.75, 0.75, 1.75));
+        assert_eq!(aovs.normal[0], Vec3::new(0.0, 0.75, 0.0));
+        assert_eq!(aovs.depth[0], 1.5);
+        assert_eq!(aovs.object_id[0], 3);
+        assert_eq!(aovs.background[0], Color::new(0.0, 0.0, 1.0));
+        assert_eq!(aovs.sky[0], Color::new(0.75, 0.75, 0.75));
+    }
+
+    #[test]
+    fn merge_keeps_strongest_id() {
+        let mut a = AovBuffer::new(1);
+        let mut b = AovBuffer::new(1);
+        a.add(0, 0.5, &AovSample { object_id: 1, ..AovSample::miss(Color::zeroes()) });
+        b.add(0, 0.7, &AovSample { object_id: 2, ..AovSample::miss(Color::zeroes()) });
+        a.merge_pixel(0, &b, 0);
+        assert_eq!(a.resolve(1, 1, &[1.2]).object_id[0], 2);
+    }
+}
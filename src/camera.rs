@@ -1,10 +1,26 @@
 use crate::ray::Ray;
-use crate::color::{Color, write_color};
-use crate::hittable::Hittable;
+use crate::color::Color;
+use crate::hittable::{Hittable, HitRecord};
 use crate::vec3::{Vec3, Point3};
-use crate::utils::random_double;
-use std::f64::INFINITY;
-use std::io::Write;
+use crate::utils::{random_double, seed_random, hash_seed};
+use crate::adaptive::{AdaptiveSampling, PixelStats, write_heatmap};
+use crate::progressive::Progressive;
+use crate::filter::Filter;
+use crate::film::Film;
+use crate::aov::AovSample;
+use crate::tile::{Tile, TileOrder, tiles};
+use crate::colorspace::{ColorSpace, TEMPERATURE_RANGE};
+use crate::projection::Projection;
+use crate::stereo::Stereo;
+use crate::lens::{Aperture, Lens, LensSystem, MILLIMETER};
+use crate::mat3::Mat3;
+use crate::crop::{CropWindow, RegionOfInterest};
+use crate::error;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 use rayon::prelude::*;
 use indicatif::{ProgressBar, ProgressStyle};
 
@@ -19,8 +35,40 @@ pub struct Camera {
     pub vup: Vec3,
     pub defocus_angle: f64,
     pub focus_dist: f64,
-    pixel_samples_scale: f64,
+    /// Optics replacing `vfov` and `defocus_angle` with physical lens parameters.
+    pub lens: Lens,
+    /// Shape of the thin lens aperture.
+    pub aperture: Aperture,
+    /// How far the exit pupil shifts toward the image corners, as a fraction of the aperture
+    /// radius, clipping the thin lens aperture into cat's-eye shapes off axis. Zero disables it.
+    pub cats_eye: f64,
+    /// Lens shift as fractions of the viewport width and height, moving the image right and
+    /// up without turning the camera, so verticals stay parallel.
+    pub lens_shift: (f64, f64),
+    /// Tilt of the plane of sharp focus in degrees about the camera's horizontal and vertical
+    /// axes, as with a Scheimpflug tilt lens. Positive values move the top and the right side
+    /// of the plane away from the camera. Only affects thin lens depth of field.
+    pub focal_plane_tilt: (f64, f64),
+    pub projection: Projection,
+    /// Renders a stereo pair into one image instead of a single view.
+    pub stereo: Option<Stereo>,
+    pub adaptive: Option<AdaptiveSampling>,
+    pub filter: Filter,
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    /// Whether films collect albedo, normal, depth and object ID layers.
+    pub aovs: bool,
+    /// Linear color space that scene colors are given in and films are rendered in.
+    pub color_space: ColorSpace,
+    /// Color temperature in kelvin of the light that should appear neutral, if white balancing.
+    pub white_balance: Option<f64>,
+    pub crop: Option<CropWindow>,
+    pub region_of_interest: Option<RegionOfInterest>,
+    /// Seeds every sample's random sequence, so a render repeats exactly for the same seed.
+    pub seed: u64,
     image_width: usize,
+    eye_width: usize,
+    eye_height: usize,
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
@@ -29,14 +77,37 @@ pub struct Camera {
     v: Vec3,
     w: Vec3,
     defocus_disk_u: Vec3,
-    defocus_disk_v: Vec3
+    defocus_disk_v: Vec3,
+    defocus_radius: f64,
+    focal_plane_normal: Vec3,
+    lens_system: Option<LensSystem>,
+    sensor_pixel: f64,
+    sample_counts: Vec<u32>,
+    color_balance: Mat3,
+    crop_tile: Option<Tile>,
+    roi_tile: Option<Tile>
 }
 
 impl Camera {
+    /// A builder starting from the book's defaults.
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder::new()
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn new(aspect_ratio: f64, image_height: usize, samples_per_pixel: u32, max_depth: u32, vfov: f64, lookfrom: Point3, lookat: Point3, vup: Vec3, defocus_angle: f64, focus_dist: f64) -> Camera {
-        if aspect_ratio <= 0.0 { panic!("Aspect ratio must be positive") };
-        if image_height <= 0 { panic!("Image height must be greater than zero") };
-        Camera {
+        Self::try_new(aspect_ratio, image_height, samples_per_pixel, max_depth, vfov, lookfrom, lookat, vup, defocus_angle, focus_dist)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new(aspect_ratio: f64, image_height: usize, samples_per_pixel: u32, max_depth: u32, vfov: f64, lookfrom: Point3, lookat: Point3, vup: Vec3, defocus_angle: f64, focus_dist: f64) -> Result<Camera, CameraError> {
+        if aspect_ratio <= 0.0 || aspect_ratio.is_nan() { return Err(CameraError::NonPositiveAspectRatio(aspect_ratio)) };
+        if image_height == 0 { return Err(CameraError::ZeroImageSize) };
+        let view = lookfrom - lookat;
+        if view.near_zero() { return Err(CameraError::LookfromIsLookat) };
+        if vup.near_zero() || Vec3::cross(vup.unit(), view.unit()).near_zero() { return Err(CameraError::VupParallelToView) };
+        Ok(Camera {
             aspect_ratio,
             image_height,
             samples_per_pixel,
@@ -47,8 +118,26 @@ impl Camera {
             vup,
             defocus_angle,
             focus_dist,
-            pixel_samples_scale: 0.0,
+            lens: Lens::default(),
+            aperture: Aperture::default(),
+            cats_eye: 0.0,
+            lens_shift: (0.0, 0.0),
+            focal_plane_tilt: (0.0, 0.0),
+            projection: Projection::default(),
+            stereo: None,
+            adaptive: None,
+            filter: Filter::default(),
+            tile_size: 32,
+            tile_order: TileOrder::default(),
+            aovs: false,
+            color_space: ColorSpace::default(),
+            white_balance: None,
+            crop: None,
+            region_of_interest: None,
+            seed: 0,
             image_width: 0,
+            eye_width: 0,
+            eye_height: 0,
             center: Vec3::zeroes(),
             pixel00_loc: Point3::zeroes(),
             pixel_delta_u: Vec3::zeroes(),
@@ -57,23 +146,42 @@ impl Camera {
             v: Vec3::zeroes(),
             w: Vec3::zeroes(),
             defocus_disk_u: Vec3::zeroes(),
-            defocus_disk_v: Vec3::zeroes()
-        }
+            defocus_disk_v: Vec3::zeroes(),
+            defocus_radius: 0.0,
+            focal_plane_normal: Vec3::zeroes(),
+            lens_system: None,
+            sensor_pixel: 0.0,
+            sample_counts: vec![],
+            color_balance: Mat3::identity(),
+            crop_tile: None,
+            roi_tile: None
+        })
     } 
 
-    fn init(&mut self) {
-        self.image_width = (self.image_height as f64 * self.aspect_ratio) as usize;
-        self.image_width = if self.image_width > 0 { self.image_width } else { 1 };
-
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
+    /// Derives the view from the public settings, failing for combinations it cannot render.
+    fn init(&mut self) -> Result<(), CameraError> {
+        self.image_width = self.image_width();
+        (self.eye_width, self.eye_height) = match self.stereo {
+            Some(stereo) => stereo.eye_size(self.image_width, self.image_height),
+            None => (self.image_width, self.image_height)
+        };
+        if self.stereo.is_some() && matches!(self.projection, Projection::Orthographic { .. }) {
+            return Err(CameraError::UnsupportedProjection("Stereo"));
+        };
+        if self.stereo.is_some_and(|stereo| !stereo.splits_evenly(self.image_width, self.image_height)) {
+            return Err(CameraError::OddStereoSize(self.image_width, self.image_height));
+        };
 
         self.center = self.lookfrom;
         
-        let theta = self.vfov.to_radians();
-        let h = f64::tan(theta / 2.0);
-        
-        let viewport_height = 2.0 * h * self.focus_dist;
-        let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
+        let viewport_height = match self.projection {
+            Projection::Orthographic { view_height } => {
+                if view_height <= 0.0 { return Err(CameraError::NonPositiveViewHeight(view_height)) };
+                view_height
+            }
+            _ => 2.0 * f64::tan(self.lens.vfov(self.vfov).to_radians() / 2.0) * self.focus_dist
+        };
+        let viewport_width = viewport_height * (self.eye_width as f64 / self.eye_height as f64);
 
         self.w = (self.lookfrom - self.lookat).unit();
         self.u = Vec3::cross(self.vup, self.w).unit();
@@ -82,41 +190,158 @@ impl Camera {
         let viewport_u = viewport_width * self.u;
         let viewport_v = viewport_height * -self.v;
 
-        self.pixel_delta_u = viewport_u / self.image_width as f64;
-        self.pixel_delta_v = viewport_v / self.image_height as f64;
+        self.pixel_delta_u = viewport_u / self.eye_width as f64;
+        self.pixel_delta_v = viewport_v / self.eye_height as f64;
 
-        let viewport_upper_left = self.center - (self.focus_dist * self.w) - viewport_u / 2.0 - viewport_v / 2.0;
+        let viewport_center = match self.projection {
+            Projection::Orthographic { .. } => self.center,
+            _ => self.center - self.focus_dist * self.w
+        };
+        let shift = self.lens_shift.0 * viewport_u - self.lens_shift.1 * viewport_v;
+        let viewport_upper_left = viewport_center + shift - viewport_u / 2.0 - viewport_v / 2.0;
         self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
 
-        let defocus_radius = self.focus_dist * f64::tan((self.defocus_angle / 2.0).to_radians());
-        self.defocus_disk_u = self.u * defocus_radius;
-        self.defocus_disk_v = self.v * defocus_radius;
+        self.defocus_radius = self.lens.defocus_radius(self.defocus_angle, self.focus_dist);
+        self.defocus_disk_u = self.u * self.defocus_radius;
+        self.defocus_disk_v = self.v * self.defocus_radius;
+
+        let (tilt, swing) = (self.focal_plane_tilt.0.to_radians(), self.focal_plane_tilt.1.to_radians());
+        self.focal_plane_normal = (tilt.cos() * swing.cos() * self.w + tilt.sin() * self.v + swing.sin() * self.u).unit();
+
+        if let Some(parameter) = self.filter.invalid_parameter() { return Err(CameraError::InvalidFilter(parameter)) };
+        if let Aperture::Polygon { blades, .. } = self.aperture {
+            if blades < 3 { return Err(CameraError::TooFewBlades(blades)) };
+        };
+
+        (self.lens_system, self.sensor_pixel) = match &self.lens {
+            Lens::Realistic { system, sensor_height } => {
+                if self.projection != Projection::Perspective { return Err(CameraError::UnsupportedProjection("Realistic lens")) };
+                let focused = system.focused(self.focus_dist).ok_or(CameraError::LensCannotFocus(self.focus_dist))?;
+                (Some(focused), MILLIMETER * sensor_height / self.eye_height as f64)
+            }
+            _ => (None, 0.0)
+        };
+
+        self.color_balance = match self.white_balance {
+            Some(temperature) if !TEMPERATURE_RANGE.contains(&temperature) => return Err(CameraError::TemperatureOutOfRange(temperature)),
+            Some(temperature) => self.color_space.white_balance(temperature),
+            None => Mat3::identity()
+        };
+
+        self.crop_tile = self.crop.map(|crop| crop.region.try_to_tile(self.image_width, self.image_height)).transpose()?;
+        self.roi_tile = self.region_of_interest.map(|roi| roi.region.try_to_tile(self.image_width, self.image_height)).transpose()?;
+        Ok(())
     }
 
     fn sample_square() -> Vec3 {
         Vec3::new(random_double() - 0.5, random_double() - 0.5, 0.0)
     }
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        let p = Vec3::random_disk();
-        self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
+    /// A random point on the lens aperture centered on `center`, for a sample at continuous
+    /// position (`x`, `y`) of the eye's image. `None` if the exit pupil blocks it.
+    fn defocus_disk_sample(&self, center: Point3, x: f64, y: f64) -> Option<Point3> {
+        let p = self.aperture.sample();
+        if self.cats_eye > 0.0 {
+            let (dx, dy) = (x - 0.5 * self.eye_width as f64, 0.5 * self.eye_height as f64 - y);
+            let corner = 0.5 * (self.eye_width as f64).hypot(self.eye_height as f64);
+            let pupil = Vec3::new(dx, dy, 0.0) * (self.cats_eye / corner);
+            if (p - pupil).length_squared() > 1.0 {
+                return None;
+            }
+        }
+        Some(center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v))
     }
 
-    fn get_ray(&self, i: usize, j: usize) -> Ray {
-        let offset = Self::sample_square();
-        let pixel_sample = self.pixel00_loc 
+    /// Where the line from `origin` along `direction` meets the plane of sharp focus, which
+    /// passes through the point `focus_dist` ahead of the camera.
+    fn focus_point(&self, origin: Point3, direction: Vec3) -> Point3 {
+        let plane_point = self.center - self.focus_dist * self.w;
+        let t = Vec3::dot(plane_point - origin, self.focal_plane_normal) / Vec3::dot(direction, self.focal_plane_normal);
+        origin + t * direction
+    }
+
+    /// The ray that leaves a realistic lens for a sample at continuous position (`x`, `y`) of
+    /// the eye's image, with the film centered behind `center`.
+    fn realistic_ray(&self, system: &LensSystem, center: Point3, x: f64, y: f64) -> Option<Ray> {
+        let film = Vec3::new(
+            -(x - 0.5 * self.eye_width as f64) * self.sensor_pixel,
+            (y - 0.5 * self.eye_height as f64) * self.sensor_pixel,
+            0.0
+        );
+        let rear = system.rear();
+        let p = rear.aperture_radius * Vec3::random_disk();
+        let exit = system.trace_from_film(Ray::new(film, Vec3::new(p.x, p.y, -rear.thickness) - film))?;
+        let (o, d) = (exit.origin, exit.direction);
+        Some(Ray::new(center + o.x * self.u + o.y * self.v + o.z * self.w, d.x * self.u + d.y * self.v + d.z * self.w))
+    }
+
+    /// The ray through pixel (`i`, `j`) at `offset` from its center, or `None` where the
+    /// projection sees nothing.
+    fn get_ray(&self, i: usize, j: usize, offset: Vec3) -> Option<Ray> {
+        let (eye, i, j, half_baseline) = match self.stereo {
+            Some(stereo) => {
+                let (eye, i, j) = stereo.eye_pixel(i, j, self.image_width, self.image_height);
+                (eye, i, j, 0.5 * stereo.interocular)
+            }
+            None => (0.0, i, j, 0.0)
+        };
+
+        let (x, y) = (i as f64 + 0.5 + offset.x, j as f64 + 0.5 + offset.y);
+        if self.projection.is_directional() {
+            let d = self.projection.direction(x, y, self.eye_width, self.eye_height)?;
+            let eye_offset = match self.projection {
+                Projection::Equirectangular => Self::ods_offset(d),
+                _ => Vec3::new(1.0, 0.0, 0.0)
+            };
+            let origin = self.center + eye * half_baseline * (eye_offset.x * self.u + eye_offset.z * self.w);
+            return Some(Ray::new(origin, d.x * self.u + d.y * self.v + d.z * self.w));
+        }
+
+        let mut pixel_sample = self.pixel00_loc 
                             + ((i as f64 + offset.x) * self.pixel_delta_u)
                             + ((j as f64 + offset.y) * self.pixel_delta_v);
-        let ray_origin = if self.defocus_angle <= 0.0 { self.center } else { self.defocus_disk_sample() };
-        let ray_direction = pixel_sample - ray_origin;
+        let mut center = self.center;
+        if let Some(stereo) = self.stereo {
+            center += eye * half_baseline * self.u;
+            pixel_sample += eye * half_baseline * (1.0 - self.focus_dist / stereo.convergence) * self.u;
+        }
 
-        Ray::new(ray_origin, ray_direction)
+        if let Some(system) = &self.lens_system {
+            return self.realistic_ray(system, center, x, y);
+        }
+
+        match self.projection {
+            Projection::Orthographic { .. } => {
+                if self.defocus_radius <= 0.0 {
+                    return Some(Ray::new(pixel_sample, -self.w));
+                }
+                let focus_point = self.focus_point(pixel_sample, -self.w);
+                let ray_origin = self.defocus_disk_sample(pixel_sample, x, y)?;
+                Some(Ray::new(ray_origin, focus_point - ray_origin))
+            }
+            _ => {
+                if self.defocus_radius <= 0.0 {
+                    return Some(Ray::new(center, pixel_sample - center));
+                }
+                let focus_point = self.focus_point(center, pixel_sample - center);
+                let ray_origin = self.defocus_disk_sample(center, x, y)?;
+                Some(Ray::new(ray_origin, focus_point - ray_origin))
+            }
+        }
     }
 
-    pub fn render(&mut self, stream: &mut dyn Write, world: &impl Hittable) {
-        self.init();
+    /// Direction, in the camera frame, from the center of an ODS eye circle to the right eye
+    /// when looking along `direction`: perpendicular to it in the horizontal plane.
+    fn ods_offset(direction: Vec3) -> Vec3 {
+        let horizontal = (direction.x * direction.x + direction.z * direction.z).sqrt();
+        if horizontal < 1e-12 {
+            return Vec3::zeroes();
+        }
+        Vec3::new(-direction.z / horizontal, 0.0, direction.x / horizontal)
+    }
 
-        let pb = ProgressBar::new(self.image_height as u64);
+    fn progress_bar(len: usize, message: &'static str) -> ProgressBar {
+        let pb = ProgressBar::new(len as u64);
         pb.set_style(
             ProgressStyle::with_template(
                 "{spinner:.green} {msg} [{elapsed_precise}/{duration_precise}] {eta_precise} {bar:100.cyan/blue} {human_pos}/{human_len} ({percent_precise}%) ({per_sec})"
@@ -124,50 +349,770 @@ impl Camera {
             .unwrap()
             .progress_chars("█▉▊▋▌▍▎▏  ")
         );
-        pb.set_message("Raytracing...");
+        pb.set_message(message);
         pb.set_position(0);
         pb.reset_eta();
+        pb
+    }
+
+    /// How many times the usual samples pixel (`i`, `j`) takes.
+    fn sample_multiplier(&self, i: usize, j: usize) -> u32 {
+        match (self.region_of_interest, self.roi_tile) {
+            (Some(roi), Some(tile)) if tile.contains(i, j) => roi.sample_multiplier,
+            _ => 1
+        }
+    }
+
+    /// Traces sample `index` of pixel (`i`, `j`), splats it into `buffer` through the
+    /// reconstruction filter and returns its color. The sample's random numbers depend only on
+    /// the seed, the pixel and the index.
+    fn trace_sample(&self, i: usize, j: usize, index: u32, world: &impl Hittable, buffer: &mut Film) -> Color {
+        seed_random(hash_seed(&[self.seed, i as u64, j as u64, index as u64]));
+        let offset = Self::sample_square();
+        let (pixel_color, aov) = match self.get_ray(i, j, offset) {
+            Some(r) if self.aovs => {
+                let (pixel_color, aov) = self.ray_color_with_aov(&r, world);
+                (pixel_color, Some(aov))
+            }
+            Some(r) => (Self::ray_color(&r, self.max_depth, world), None),
+            None => (Color::zeroes(), self.aovs.then(|| AovSample::miss(Color::zeroes())))
+        };
+        let pixel_color = self.color_balance * pixel_color;
+        let aov = aov.map(|aov| AovSample { background: self.color_balance * aov.background, sky: self.color_balance * aov.sky, ..aov });
+        buffer.splat_sample(&self.filter, i as f64 + 0.5 + offset.x, j as f64 + 0.5 + offset.y, pixel_color, aov.as_ref());
+        pixel_color
+    }
+
+    /// The pixels that samples taken inside `tile` can splat into.
+    fn tile_buffer(&self, tile: &Tile) -> Film {
+        let reach = self.filter.pixel_reach();
+        let x0 = tile.x0.saturating_sub(reach);
+        let y0 = tile.y0.saturating_sub(reach);
+        let x1 = usize::min(tile.x1 + reach, self.image_width);
+        let y1 = usize::min(tile.y1 + reach, self.image_height);
+        let film = Film::with_origin(x0, y0, x1 - x0, y1 - y0);
+        if self.aovs { film.with_aovs() } else { film }
+    }
+
+    fn film(&self) -> Film {
+        let film = Film::new(self.image_width, self.image_height).in_color_space(self.color_space);
+        if self.aovs { film.with_aovs() } else { film }
+    }
+
+    /// The tiles to trace: the whole image, or when cropping, just the pixels whose samples
+    /// reach the crop window.
+    fn tiles(&self) -> Vec<Tile> {
+        let tiles = tiles(self.image_width, self.image_height, self.tile_size, self.tile_order);
+        match self.crop_tile {
+            Some(crop) => {
+                let reach = self.filter.pixel_reach();
+                let margin = Tile {
+                    x0: crop.x0.saturating_sub(reach),
+                    y0: crop.y0.saturating_sub(reach),
+                    x1: usize::min(crop.x1 + reach, self.image_width),
+                    y1: usize::min(crop.y1 + reach, self.image_height)
+                };
+                tiles.iter().filter_map(|tile| tile.intersect(&margin)).collect()
+            }
+            None => tiles
+        }
+    }
+
+    /// Renders each tile with its own state into a private buffer on the thread pool, then
+    /// merges the buffers into `film` in the order given, so that the sums do not depend on
+    /// which tiles finish first.
+    fn render_tiles<'a, S: Send>(
+        &self,
+        work: impl Iterator<Item = (&'a Tile, S)>,
+        film: &mut Film,
+        trace: impl Fn(&Tile, S, &mut Film) + Send + Sync
+    ) {
+        let buffers: Vec<Film> = work.collect::<Vec<_>>().into_par_iter().map(|(tile, state)| {
+            let mut buffer = self.tile_buffer(tile);
+            trace(tile, state, &mut buffer);
+            buffer
+        }).collect();
+        for buffer in &buffers {
+            film.merge(buffer);
+        }
+    }
 
-        print!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-
-        for j in 0..self.image_height {
-            pb.set_position(j as u64);
-            let pixel_colors: Vec<_> = (0..self.image_width)
-                .into_par_iter()
-                .map(|i| {
-                    let mut pixel_color = Color::zeroes();
-                    for _sample in 0..self.samples_per_pixel {
-                        let r = self.get_ray(i, j);
-                        pixel_color += Self::ray_color(&r, self.max_depth, world);
+    /// The part of `film` the camera outputs: all of it, or the crop window, either alone or
+    /// on an otherwise black frame.
+    fn output(&self, film: &Film) -> Film {
+        match (self.crop, self.crop_tile) {
+            (Some(crop), Some(tile)) => {
+                let cropped = film.cropped(tile.x0, tile.y0, tile.width(), tile.height());
+                if !crop.full_frame {
+                    return cropped;
+                }
+                let mut frame = self.film();
+                frame.merge(&cropped);
+                frame
+            }
+            _ => film.clone()
+        }
+    }
+
+    /// Records `samples` times their multiplier for the pixels of `tiles` and none for the others.
+    fn record_sample_counts(&mut self, tiles: &[Tile], samples: u32) {
+        let mut sample_counts = vec![0; self.image_width * self.image_height];
+        for tile in tiles {
+            for (i, j) in tile.pixels() {
+                sample_counts[j * self.image_width + i] = samples * self.sample_multiplier(i, j);
+            }
+        }
+        self.sample_counts = sample_counts;
+    }
+
+    /// Renders `world` into a film of linear RGB pixels.
+    pub fn render(&mut self, world: &impl Hittable) -> error::Result<Film> {
+        self.init()?;
+
+        let pb = Self::progress_bar(0, "Raytracing...");
+
+        let film = match self.adaptive {
+            Some(settings) => self.render_adaptive(world, &settings, &pb),
+            None => self.render_uniform(world, &pb)
+        };
+
+        pb.finish_with_message("Done.");
+        Ok(self.output(&film))
+    }
+
+    fn render_uniform(&mut self, world: &impl Hittable, pb: &ProgressBar) -> Film {
+        let tiles = self.tiles();
+        let mut film = self.film();
+
+        pb.set_length(tiles.len() as u64);
+        self.render_tiles(tiles.iter().map(|tile| (tile, ())), &mut film, |tile, (), buffer| {
+            for (i, j) in tile.pixels() {
+                for index in 0..self.samples_per_pixel * self.sample_multiplier(i, j) {
+                    self.trace_sample(i, j, index, world, buffer);
+                }
+            }
+            pb.inc(1);
+        });
+
+        self.record_sample_counts(&tiles, self.samples_per_pixel);
+        film
+    }
+
+    /// `settings` scaled for pixel (`i`, `j`) by its sample multiplier.
+    fn pixel_settings(&self, settings: &AdaptiveSampling, i: usize, j: usize) -> AdaptiveSampling {
+        let multiplier = self.sample_multiplier(i, j);
+        AdaptiveSampling {
+            min_samples: settings.min_samples * multiplier,
+            max_samples: settings.max_samples * multiplier,
+            batch_size: settings.batch_size.max(1) * multiplier,
+            ..*settings
+        }
+    }
+
+    fn render_adaptive(&mut self, world: &impl Hittable, settings: &AdaptiveSampling, pb: &ProgressBar) -> Film {
+        let tiles = self.tiles();
+        let mut stats: Vec<Vec<PixelStats>> = tiles
+            .iter()
+            .map(|tile| vec![PixelStats::new(); tile.width() * tile.height()])
+            .collect();
+        let mut film = self.film();
+        let pixel_count: usize = tiles.iter().map(|tile| tile.width() * tile.height()).sum();
+
+        pb.set_length(pixel_count as u64);
+        pb.set_message("Adaptive raytracing...");
+
+        let mut first_batch = true;
+        loop {
+            let converged = AtomicUsize::new(0);
+            self.render_tiles(tiles.iter().zip(stats.iter_mut()), &mut film, |tile, tile_stats, buffer| {
+                for ((i, j), pixel) in tile.pixels().zip(tile_stats.iter_mut()) {
+                    let settings = self.pixel_settings(settings, i, j);
+                    if !pixel.converged(&settings) {
+                        let batch = if first_batch { settings.min_samples } else { settings.batch_size };
+                        let samples = u32::min(batch, settings.max_samples - pixel.samples);
+                        for index in pixel.samples..pixel.samples + samples {
+                            pixel.add(self.trace_sample(i, j, index, world, buffer));
+                        }
                     }
-                    pixel_color
-                }) 
-                .collect();
-            for pixel_color in pixel_colors {
-                write_color(stream, self.pixel_samples_scale * pixel_color);
+                    if pixel.converged(&settings) {
+                        converged.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+
+            let converged = converged.into_inner();
+            pb.set_position(converged as u64);
+            if converged == pixel_count {
+                break;
+            }
+            first_batch = false;
+        }
+
+        self.sample_counts = vec![0; self.image_width * self.image_height];
+        for (tile, tile_stats) in tiles.iter().zip(&stats) {
+            for ((i, j), pixel) in tile.pixels().zip(tile_stats) {
+                self.sample_counts[j * self.image_width + i] = pixel.samples;
+            }
+        }
+        film
+    }
+
+    /// Renders full-image passes of increasing sample counts into an accumulating film,
+    /// handing the film so far to `on_update` after each pass, or once `settings.interval`
+    /// has elapsed. Rendering stops early if `on_update` breaks, and the final film is returned.
+    pub fn render_progressive(
+        &mut self,
+        world: &impl Hittable,
+        settings: &Progressive,
+        mut on_update: impl FnMut(&Film, u32) -> ControlFlow<()>
+    ) -> error::Result<Film> {
+        if settings.first_pass_samples == 0 {
+            return Err(error::Error::invalid_value("First pass samples", "must be at least one"));
+        }
+        self.init()?;
+
+        let pb = Self::progress_bar(self.samples_per_pixel as usize, "Progressive raytracing...");
+
+        let tiles = self.tiles();
+        let mut film = self.film();
+        let mut samples = 0;
+        let mut pass_samples = settings.first_pass_samples;
+        let mut last_update = Instant::now();
+
+        while samples < self.samples_per_pixel {
+            let pass = u32::min(pass_samples, self.samples_per_pixel - samples);
+            self.render_tiles(tiles.iter().map(|tile| (tile, ())), &mut film, |tile, (), buffer| {
+                for (i, j) in tile.pixels() {
+                    let multiplier = self.sample_multiplier(i, j);
+                    for index in samples * multiplier..(samples + pass) * multiplier {
+                        self.trace_sample(i, j, index, world, buffer);
+                    }
+                }
+            });
+            samples += pass;
+            pass_samples = samples;
+            pb.set_position(samples as u64);
+
+            let finished = samples == self.samples_per_pixel;
+            let due = settings.interval.is_none_or(|interval| last_update.elapsed() >= interval);
+            if finished || due {
+                last_update = Instant::now();
+                if on_update(&self.output(&film), samples).is_break() {
+                    break;
+                }
             }
         }
 
         pb.finish_with_message("Done.");
+        self.record_sample_counts(&tiles, samples);
+        Ok(self.output(&film))
+    }
+
+    /// Width of the image in pixels, derived from `image_height` and `aspect_ratio`.
+    pub fn image_width(&self) -> usize {
+        (self.image_height as f64 * self.aspect_ratio).max(1.0) as usize
+    }
+
+    /// Samples taken per pixel in the last render, in scanline order.
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.sample_counts
+    }
+
+    /// Writes a heatmap of the samples taken per pixel in the last render.
+    pub fn write_sample_heatmap(&self, stream: &mut dyn Write) -> io::Result<()> {
+        let max_samples = self.adaptive.map_or(self.samples_per_pixel, |settings| settings.max_samples);
+        write_heatmap(stream, self.image_width, self.image_height, &self.sample_counts, max_samples)
     }
 
     fn ray_color(r: &Ray, depth: u32, world: &impl Hittable) -> Color {
-        if depth <= 0 {
+        if depth == 0 {
             return Color::zeroes();
         }
 
-        let rec = world.hit(r, (0.001, INFINITY));
+        let rec = world.hit(r, (0.001, f64::INFINITY));
+        Self::shade(r, rec.as_ref(), depth, world)
+    }
+
+    /// Like `ray_color` for a camera ray, also returning the AOVs of its first hit.
+    fn ray_color_with_aov(&self, r: &Ray, world: &impl Hittable) -> (Color, AovSample) {
+        if self.max_depth == 0 {
+            return (Color::zeroes(), AovSample::miss(Color::zeroes()));
+        }
+
+        let rec = world.hit(r, (0.001, f64::INFINITY));
+        let pixel_color = Self::shade(r, rec.as_ref(), self.max_depth, world);
+        let aov = match &rec {
+            Some(hit) => AovSample {
+                albedo: hit.mat.albedo(hit),
+                normal: hit.normal,
+                depth: hit.t * r.direction.length(),
+                object_id: hit.object_id,
+                background: Color::zeroes(),
+                sky: pixel_color
+            },
+            None => AovSample::miss(pixel_color)
+        };
+        (pixel_color, aov)
+    }
+
+    /// Color arriving along `r`, given its closest hit `rec`.
+    fn shade(r: &Ray, rec: Option<&HitRecord>, depth: u32, world: &impl Hittable) -> Color {
         if let Some(hit) = rec {
             let mut scattered = Ray::new(Point3::zeroes(), Vec3::zeroes());
             let mut attenuation = Color::zeroes();
-            if hit.mat.scatter(r, &hit, &mut attenuation, &mut scattered) {
+            if hit.mat.scatter(r, hit, &mut attenuation, &mut scattered) {
                 return attenuation * Self::ray_color(&scattered, depth-1, world);
             }
             return Color::zeroes();
         }
 
+        Self::background(r)
+    }
+
+    fn background(r: &Ray) -> Color {
         let unit_direction = r.direction.unit();
         let a = 0.5 * (unit_direction.y + 1.0);
-        return (1.0-a) * Color::new(1.0, 1.0, 1.0) + a*Color::new(0.5, 0.7, 1.0);
+        (1.0-a) * Color::new(1.0, 1.0, 1.0) + a*Color::new(0.5, 0.7, 1.0)
     }
-}
\ No newline at end of file
+}
+
+/// Why a camera configuration was rejected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraError {
+    NonPositiveAspectRatio(f64),
+    ZeroImageSize,
+    /// `lookfrom` and `lookat` are the same point, so there is no view direction.
+    LookfromIsLookat,
+    /// `vup` is zero or parallel to the view direction, so it cannot orient the image.
+    VupParallelToView,
+    NonPositiveViewHeight(f64),
+    /// The named feature does not work with the camera's projection.
+    UnsupportedProjection(&'static str),
+    /// The realistic lens cannot bring this distance into focus.
+    LensCannotFocus(f64),
+    /// A crop window or region of interest covers no pixel of the image.
+    EmptyRegion,
+    /// The white balance temperature in kelvin is outside the range it can be computed for.
+    TemperatureOutOfRange(f64),
+    /// The image side split between the two stereo eyes has an odd number of pixels.
+    OddStereoSize(usize, usize),
+    /// A polygonal aperture has fewer than three blades.
+    TooFewBlades(u32),
+    /// The named parameter of the reconstruction filter is not finite and positive.
+    InvalidFilter(&'static str)
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraError::NonPositiveAspectRatio(aspect_ratio) => write!(f, "Aspect ratio must be positive, got {}", aspect_ratio),
+            CameraError::ZeroImageSize => write!(f, "Image width and height must be greater than zero"),
+            CameraError::LookfromIsLookat => write!(f, "Camera lookfrom and lookat must be different points"),
+            CameraError::VupParallelToView => write!(f, "Camera vup must not be parallel to the view direction"),
+            CameraError::NonPositiveViewHeight(view_height) => write!(f, "Orthographic view height must be positive, got {}", view_height),
+            CameraError::UnsupportedProjection(feature) => write!(f, "{} needs a perspective projection", feature),
+            CameraError::LensCannotFocus(focus_dist) => write!(f, "Lens cannot focus at {}", focus_dist),
+            CameraError::EmptyRegion => write!(f, "Region must cover at least one pixel of the image"),
+            CameraError::TemperatureOutOfRange(temperature) => write!(f, "White balance must be between 1667 and 25000 K, got {}", temperature),
+            CameraError::OddStereoSize(width, height) => {
+                write!(f, "Stereo images must split evenly between the eyes, got {}x{}", width, height)
+            }
+            CameraError::TooFewBlades(blades) => write!(f, "Polygonal apertures need at least three blades, got {}", blades),
+            CameraError::InvalidFilter(parameter) => write!(f, "Filter {} must be finite and positive", parameter)
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
+/// The dimension an image size is given in. The other one follows from the aspect ratio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageSize {
+    Width(usize),
+    Height(usize)
+}
+
+/// Configures a camera by name, starting from the book's defaults, and checks the result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraBuilder {
+    aspect_ratio: f64,
+    image_size: ImageSize,
+    samples_per_pixel: u32,
+    max_depth: u32,
+    vfov: f64,
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vec3,
+    defocus_angle: f64,
+    focus_dist: f64,
+    seed: u64
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        CameraBuilder {
+            aspect_ratio: 1.0,
+            image_size: ImageSize::Width(100),
+            samples_per_pixel: 10,
+            max_depth: 10,
+            vfov: 90.0,
+            lookfrom: Point3::zeroes(),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            seed: 0
+        }
+    }
+}
+
+impl CameraBuilder {
+    pub fn new() -> CameraBuilder {
+        CameraBuilder::default()
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    /// Sets the image width, replacing any height set before.
+    pub fn image_width(mut self, width: usize) -> Self {
+        self.image_size = ImageSize::Width(width);
+        self
+    }
+
+    /// Sets the image height, replacing any width set before.
+    pub fn image_height(mut self, height: usize) -> Self {
+        self.image_size = ImageSize::Height(height);
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: u32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    pub fn lookfrom(mut self, lookfrom: Point3) -> Self {
+        self.lookfrom = lookfrom;
+        self
+    }
+
+    pub fn lookat(mut self, lookat: Point3) -> Self {
+        self.lookat = lookat;
+        self
+    }
+
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    pub fn defocus_angle(mut self, defocus_angle: f64) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The configured camera. A width is kept exactly, with the height rounded down from it
+    /// and the aspect ratio adjusted to match.
+    pub fn build(&self) -> Result<Camera, CameraError> {
+        if self.aspect_ratio <= 0.0 || self.aspect_ratio.is_nan() {
+            return Err(CameraError::NonPositiveAspectRatio(self.aspect_ratio));
+        }
+        let (aspect_ratio, image_height) = match self.image_size {
+            ImageSize::Width(0) | ImageSize::Height(0) => return Err(CameraError::ZeroImageSize),
+            ImageSize::Width(width) => {
+                let height = ((width as f64 / self.aspect_ratio) as usize).max(1);
+                let mut aspect_ratio = width as f64 / height as f64;
+                while ((height as f64 * aspect_ratio) as usize) < width {
+                    aspect_ratio = aspect_ratio.next_up();
+                }
+                (aspect_ratio, height)
+            }
+            ImageSize::Height(height) => (self.aspect_ratio, height)
+        };
+        let mut camera = Camera::try_new(
+            aspect_ratio,
+            image_height,
+            self.samples_per_pixel,
+            self.max_depth,
+            self.vfov,
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.defocus_angle,
+            self.focus_dist
+        )?;
+        camera.seed = self.seed;
+        Ok(camera)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projection::FisheyeMapping;
+    use crate::stereo::StereoLayout;
+    use crate::crop::Region;
+    use crate::geometry::{Scene, Sphere};
+    use crate::material::{Lambertian, Metal};
+    use std::sync::Arc;
+
+    fn camera(projection: Projection) -> Camera {
+        let mut cam = Camera::new(2.0, 50, 1, 10, 90.0, Point3::zeroes(), Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 0.0, 1.0);
+        cam.projection = projection;
+        cam.init().unwrap();
+        cam
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let cam = camera(Projection::Orthographic { view_height: 4.0 });
+        let corner = cam.get_ray(0, 0, Vec3::new(-0.5, -0.5, 0.0)).unwrap();
+        let center = cam.get_ray(50, 25, Vec3::new(-0.5, -0.5, 0.0)).unwrap();
+        assert_eq!(corner.direction, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(center.direction, corner.direction);
+        assert!((corner.origin - Point3::new(-4.0, 2.0, 0.0)).length() < 1e-12);
+        assert!(center.origin.length() < 1e-12);
+    }
+
+    #[test]
+    fn perspective_rays_share_origin() {
+        let cam = camera(Projection::Perspective);
+        let corner = cam.get_ray(0, 0, Vec3::new(-0.5, -0.5, 0.0)).unwrap();
+        assert_eq!(corner.origin, Point3::zeroes());
+        assert!((corner.direction - Vec3::new(-2.0, 1.0, -1.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn directional_rays_use_camera_frame() {
+        let mut cam = camera(Projection::Equirectangular);
+        cam.lookat = Point3::new(1.0, 0.0, 0.0);
+        cam.init().unwrap();
+        let forward = cam.get_ray(50, 25, Vec3::new(-0.5, -0.5, 0.0)).unwrap();
+        assert_eq!(forward.origin, Point3::zeroes());
+        assert!((forward.direction - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+
+        let cam = camera(Projection::Fisheye { fov: 90.0, mapping: FisheyeMapping::Equidistant });
+        assert!(cam.get_ray(0, 0, Vec3::zeroes()).is_none());
+    }
+
+    #[test]
+    fn lens_shift_keeps_view_direction() {
+        let mut cam = camera(Projection::Perspective);
+        cam.lens_shift = (0.0, 0.25);
+        cam.init().unwrap();
+        assert_eq!(cam.w, Vec3::new(0.0, 0.0, 1.0));
+        let center = cam.get_ray(50, 25, Vec3::new(-0.5, -0.5, 0.0)).unwrap();
+        assert!((center.direction - Vec3::new(0.0, 0.5, -1.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn tilted_focal_plane() {
+        let mut cam = camera(Projection::Perspective);
+        cam.defocus_angle = 10.0;
+        cam.focal_plane_tilt = (20.0, 0.0);
+        cam.init().unwrap();
+        let pinhole = Vec3::new(0.0, 0.98, -1.0);
+        let focus = pinhole / (1.0 - pinhole.y * 20f64.to_radians().tan());
+        for _ in 0..10 {
+            let top = cam.get_ray(50, 0, Vec3::new(-0.5, 0.0, 0.0)).unwrap();
+            assert!(top.origin.length() > 0.0);
+            assert!(Vec3::cross(focus - top.origin, top.direction.unit()).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn stereo_eyes_converge() {
+        let mut cam = Camera::new(2.0, 50, 1, 10, 90.0, Point3::zeroes(), Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 0.0, 1.0);
+        cam.stereo = Some(Stereo::new(StereoLayout::SideBySide, 0.2, 4.0));
+        cam.init().unwrap();
+        let left = cam.get_ray(25, 25, Vec3::new(-0.5, -0.5, 0.0)).unwrap();
+        let right = cam.get_ray(75, 25, Vec3::new(-0.5, -0.5, 0.0)).unwrap();
+        assert_eq!(left.origin, Point3::new(-0.1, 0.0, 0.0));
+        assert_eq!(right.origin, Point3::new(0.1, 0.0, 0.0));
+        assert!((left.at(4.0) - right.at(4.0)).length() < 1e-12);
+        assert!((left.at(4.0) - Point3::new(0.0, 0.0, -4.0)).length() < 1e-12);
+
+        cam.aspect_ratio = 2.02;
+        assert_eq!(cam.init(), Err(CameraError::OddStereoSize(101, 50)));
+    }
+
+    #[test]
+    fn ods_eyes_follow_circle() {
+        let mut cam = Camera::new(2.0, 50, 1, 10, 90.0, Point3::zeroes(), Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 0.0, 1.0);
+        cam.projection = Projection::Equirectangular;
+        cam.stereo = Some(Stereo::new(StereoLayout::TopBottom, 0.2, 4.0));
+        cam.init().unwrap();
+        let front = cam.get_ray(50, 37, Vec3::new(-0.5, 0.0, 0.0)).unwrap();
+        assert!((front.origin - Point3::new(0.1, 0.0, 0.0)).length() < 1e-12);
+        let side = cam.get_ray(75, 37, Vec3::new(-0.5, 0.0, 0.0)).unwrap();
+        assert!((side.origin - Point3::new(0.0, 0.0, 0.1)).length() < 1e-12);
+        assert!((side.direction - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+    }
+
+    fn small_world() -> Scene {
+        let mut world = Scene::new();
+        world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3))))));
+        world.add(Box::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.3)))));
+        world
+    }
+
+    fn crop_camera(crop: Option<CropWindow>) -> Camera {
+        let mut cam = Camera::new(1.5, 24, 4, 5, 90.0, Point3::zeroes(), Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 0.0, 1.0);
+        cam.filter = Filter::mitchell();
+        cam.tile_size = 8;
+        cam.crop = crop;
+        cam
+    }
+
+    #[test]
+    fn crop_matches_full_render() {
+        let world = small_world();
+        let full = crop_camera(None).render(&world).unwrap();
+
+        let region = Region::Pixels { x0: 5, y0: 7, x1: 23, y1: 15 };
+        let cropped = crop_camera(Some(CropWindow::new(region, false))).render(&world).unwrap();
+        assert_eq!((cropped.width, cropped.height), (18, 8));
+        for (i, j) in (Tile { x0: 5, y0: 7, x1: 23, y1: 15 }).pixels() {
+            assert_eq!(cropped.pixel(i - 5, j - 7), full.pixel(i, j));
+        }
+
+        let framed = crop_camera(Some(CropWindow::new(region, true))).render(&world).unwrap();
+        assert_eq!((framed.width, framed.height), (full.width, full.height));
+        assert_eq!(framed.pixel(10, 10), full.pixel(10, 10));
+        assert_eq!(framed.pixel(4, 10), Color::zeroes());
+        assert_eq!(framed.pixel(10, 15), Color::zeroes());
+    }
+
+    #[test]
+    fn light_groups_add_up_to_beauty() {
+        let mut cam = crop_camera(None);
+        cam.aovs = true;
+        let film = cam.render(&small_world()).unwrap();
+        let aovs = film.aovs().unwrap();
+        for (index, pixel_color) in film.pixels().enumerate() {
+            assert!((aovs.background[index] + aovs.sky[index] - pixel_color).length() < 1e-9);
+        }
+        assert!(aovs.background.iter().any(|&color| color.length() > 0.0));
+        assert!(aovs.sky.iter().any(|&color| color.length() > 0.0));
+    }
+
+    #[test]
+    fn region_of_interest_takes_more_samples() {
+        let mut cam = crop_camera(None);
+        cam.region_of_interest = Some(RegionOfInterest::new(Region::Normalized { x0: 0.0, y0: 0.0, x1: 0.5, y1: 0.5 }, 3));
+        cam.render(&small_world()).unwrap();
+        assert_eq!(cam.sample_counts()[0], 12);
+        assert_eq!(cam.sample_counts()[35], 4);
+    }
+
+    #[test]
+    fn progressive_passes_double() {
+        let world = small_world();
+        let mut cam = crop_camera(None);
+        cam.samples_per_pixel = 5;
+        let mut updates = vec![];
+        let film = cam.render_progressive(&world, &Progressive::new(1, None), |_, samples| {
+            updates.push(samples);
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert_eq!(updates, [1, 2, 4, 5]);
+
+        let mut plain = crop_camera(None);
+        plain.samples_per_pixel = 5;
+        let full = plain.render(&world).unwrap();
+        for (progressive, plain) in film.pixels().zip(full.pixels()) {
+            assert!((progressive - plain).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn progressive_updates_are_throttled() {
+        let world = small_world();
+        let mut updates = vec![];
+        let settings = Progressive::new(1, Some(std::time::Duration::from_secs(3600)));
+        crop_camera(None).render_progressive(&world, &settings, |_, samples| {
+            updates.push(samples);
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert_eq!(updates, [4]);
+    }
+
+    #[test]
+    fn progressive_stops_when_asked() {
+        let mut cam = crop_camera(None);
+        let mut updates = 0;
+        cam.render_progressive(&small_world(), &Progressive::new(2, None), |_, _| {
+            updates += 1;
+            ControlFlow::Break(())
+        }).unwrap();
+        assert_eq!(updates, 1);
+        assert!(cam.sample_counts().iter().all(|&count| count == 2));
+
+        let settings = Progressive { first_pass_samples: 0, interval: None };
+        assert!(cam.render_progressive(&small_world(), &settings, |_, _| ControlFlow::Continue(())).is_err());
+        assert!(Progressive::try_new(0, None).is_err());
+    }
+
+    #[test]
+    fn builder_keeps_requested_width() {
+        let cam = Camera::builder().aspect_ratio(1.5).image_width(100).build().unwrap();
+        assert_eq!((cam.image_width(), cam.image_height), (100, 66));
+
+        let cam = Camera::builder().aspect_ratio(16.0 / 9.0).image_height(720).vfov(20.0).build().unwrap();
+        assert_eq!((cam.image_width(), cam.image_height, cam.vfov), (1280, 720, 20.0));
+        assert_eq!(cam.focus_dist, 10.0);
+    }
+
+    #[test]
+    fn builder_rejects_bad_configurations() {
+        assert_eq!(Camera::builder().aspect_ratio(0.0).build().err(), Some(CameraError::NonPositiveAspectRatio(0.0)));
+        assert_eq!(Camera::builder().image_height(0).build().err(), Some(CameraError::ZeroImageSize));
+        assert_eq!(Camera::builder().lookat(Point3::zeroes()).build().err(), Some(CameraError::LookfromIsLookat));
+        let vup = Vec3::new(0.0, 0.0, 2.0);
+        assert_eq!(Camera::builder().vup(vup).build().err(), Some(CameraError::VupParallelToView));
+    }
+
+    #[test]
+    fn render_reports_configuration_errors() {
+        let mut cam = crop_camera(Some(CropWindow::new(Region::Pixels { x0: 40, y0: 0, x1: 50, y1: 10 }, false)));
+        assert!(matches!(cam.render(&small_world()), Err(error::Error::Camera(CameraError::EmptyRegion))));
+
+        let mut cam = crop_camera(None);
+        cam.projection = Projection::Orthographic { view_height: 0.0 };
+        assert!(matches!(cam.render(&small_world()), Err(error::Error::Camera(CameraError::NonPositiveViewHeight(_)))));
+
+        let mut cam = crop_camera(None);
+        cam.white_balance = Some(1000.0);
+        assert_eq!(cam.init(), Err(CameraError::TemperatureOutOfRange(1000.0)));
+
+        let mut cam = crop_camera(None);
+        cam.aperture = Aperture::Polygon { blades: 2, rotation: 0.0 };
+        assert_eq!(cam.init(), Err(CameraError::TooFewBlades(2)));
+
+        let mut cam = crop_camera(None);
+        cam.filter = Filter::Lanczos { radius: 3.0, tau: 0.0 };
+        assert_eq!(cam.init(), Err(CameraError::InvalidFilter("tau")));
+    }
+}
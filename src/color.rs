@@ -1,29 +1,91 @@
 use crate::vec3::Vec3;
 use crate::utils::clamp;
-use std::io::{Write};
+use std::io::{self, Write};
 
 pub type Color = Vec3;
 
-fn linear_to_gamma(linear_component: f64) -> f64 {
-    if linear_component > 0.0 {
-        linear_component.sqrt()
-    } else {
+/// Encodes a linear component with the piecewise sRGB transfer function.
+pub fn linear_to_srgb(linear_component: f64) -> f64 {
+    if linear_component <= 0.0 {
         0.0
+    } else if linear_component <= 0.0031308 {
+        12.92 * linear_component
+    } else {
+        1.055 * linear_component.powf(1.0 / 2.4) - 0.055
     }
 }
 
-pub fn write_color(stream: &mut dyn Write, pixel_color: Color) {
-    let Color { x, y, z } = pixel_color;
+/// Decodes an sRGB-encoded component back to linear.
+pub fn srgb_to_linear(encoded_component: f64) -> f64 {
+    if encoded_component <= 0.04045 {
+        encoded_component / 12.92
+    } else {
+        ((encoded_component + 0.055) / 1.055).powf(2.4)
+    }
+}
 
-    let r = linear_to_gamma(x);
-    let g = linear_to_gamma(y);
-    let b = linear_to_gamma(z);
+/// Relative luminance of a linear Rec. 709 color.
+pub fn luminance(color: Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
 
+/// Encodes `pixel_color` as sRGB and quantizes it to 8 bits per channel.
+pub fn to_bytes(pixel_color: Color) -> [u8; 3] {
     const BOUNDS: (f64, f64) = (0.000, 0.999);
-    let rbyte = (255.999 * clamp(r, BOUNDS)) as i32;
-    let gbyte = (255.999 * clamp(g, BOUNDS)) as i32;
-    let bbyte = (255.999 * clamp(b, BOUNDS)) as i32;
+    let quantize = |linear_component| (255.999 * clamp(linear_to_srgb(linear_component), BOUNDS)) as u8;
+    [quantize(pixel_color.x), quantize(pixel_color.y), quantize(pixel_color.z)]
+}
+
+/// Encodes `pixel_color` as sRGB and quantizes it to 16 bits per channel.
+pub fn to_words(pixel_color: Color) -> [u16; 3] {
+    const BOUNDS: (f64, f64) = (0.000, 0.99999);
+    let quantize = |linear_component| (65535.999 * clamp(linear_to_srgb(linear_component), BOUNDS)) as u16;
+    [quantize(pixel_color.x), quantize(pixel_color.y), quantize(pixel_color.z)]
+}
+
+/// Quantizes `value` to 8 bits per channel without a transfer function, for data such as
+/// normals that are not colors.
+pub fn to_linear_bytes(value: Color) -> [u8; 3] {
+    let quantize = |component: f64| (255.0 * clamp(component, (0.0, 1.0))).round() as u8;
+    [quantize(value.x), quantize(value.y), quantize(value.z)]
+}
+
+/// Quantizes `value` to 16 bits per channel without a transfer function.
+pub fn to_linear_words(value: Color) -> [u16; 3] {
+    let quantize = |component: f64| (65535.0 * clamp(component, (0.0, 1.0))).round() as u16;
+    [quantize(value.x), quantize(value.y), quantize(value.z)]
+}
+
+/// Encodes the luminance of `pixel_color` as sRGB and quantizes it to 8 bits.
+pub fn to_gray_byte(pixel_color: Color) -> u8 {
+    let gray = luminance(pixel_color);
+    to_bytes(Color::new(gray, gray, gray))[0]
+}
 
-    writeln!(stream, "{} {} {}", rbyte, gbyte, bbyte);
+pub fn write_color(stream: &mut dyn Write, pixel_color: Color) -> io::Result<()> {
+    let [rbyte, gbyte, bbyte] = to_bytes(pixel_color);
+    writeln!(stream, "{} {} {}", rbyte, gbyte, bbyte)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantize() {
+        assert_eq!(to_bytes(Color::new(0.0, 0.25, 1.0)), [0, 137, 255]);
+        assert_eq!(to_bytes(Color::new(-1.0, 4.0, 0.0)), [0, 255, 0]);
+        assert_eq!(to_words(Color::new(0.0, 0.25, 1.0)), [0, 35199, 65535]);
+        assert_eq!(to_gray_byte(Color::ones()), 255);
+        assert_eq!(to_linear_bytes(Color::new(0.0, 0.5, 2.0)), [0, 128, 255]);
+        assert_eq!(to_linear_words(Color::new(-1.0, 0.5, 1.0)), [0, 32768, 65535]);
+    }
+
+    #[test]
+    fn srgb_transfer() {
+        assert_eq!(linear_to_srgb(0.002), 12.92 * 0.002);
+        assert!((linear_to_srgb(0.0031308) - 0.0404500).abs() < 1e-6);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-12);
+        assert!((srgb_to_linear(linear_to_srgb(0.2)) - 0.2).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::color::Color;
+use crate::film::Film;
+use crate::mat3::Mat3;
+use crate::vec3::Vec3;
+use std::ops::RangeInclusive;
+
+/// Chromaticity coordinates of the D65 white point.
+pub const D65: (f64, f64) = (0.3127, 0.3290);
+/// Chromaticity coordinates of the ACES white point, close to D60.
+pub const ACES_WHITE: (f64, f64) = (0.32168, 0.33767);
+
+/// Linear RGB color spaces that scenes can be rendered in and images written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// Linear sRGB, which shares its primaries and D65 white with Rec. 709.
+    #[default]
+    LinearSrgb,
+    Rec2020,
+    /// The ACES AP1 primaries with the ACES white point.
+    AcesCg
+}
+
+impl ColorSpace {
+    /// Chromaticities of the red, green and blue primaries.
+    pub fn primaries(self) -> [(f64, f64); 3] {
+        match self {
+            ColorSpace::LinearSrgb => [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)],
+            ColorSpace::Rec2020 => [(0.708, 0.292), (0.170, 0.797), (0.131, 0.046)],
+            ColorSpace::AcesCg => [(0.713, 0.293), (0.165, 0.830), (0.128, 0.044)]
+        }
+    }
+
+    /// Chromaticities of the red, green and blue primaries and of the white point, as image
+    /// files record them.
+    pub fn chromaticities(self) -> [(f64, f64); 4] {
+        let [red, green, blue] = self.primaries();
+        [red, green, blue, self.white()]
+    }
+
+    pub fn white(self) -> (f64, f64) {
+        match self {
+            ColorSpace::LinearSrgb | ColorSpace::Rec2020 => D65,
+            ColorSpace::AcesCg => ACES_WHITE
+        }
+    }
+
+    /// Matrix taking colors in this space to CIE XYZ, with white mapping to Y = 1.
+    pub fn to_xyz(self) -> Mat3 {
+        let [r, g, b] = self.primaries().map(xy_to_xyz);
+        let primaries = Mat3::new([[r.x, g.x, b.x], [r.y, g.y, b.y], [r.z, g.z, b.z]]);
+        primaries * Mat3::diagonal(primaries.inverse() * xy_to_xyz(self.white()))
+    }
+
+    pub fn from_xyz(self) -> Mat3 {
+        self.to_xyz().inverse()
+    }
+
+    /// Matrix converting colors from this space into `target`, adapting between white points
+    /// with the Bradford transform.
+    pub fn conversion(self, target: ColorSpace) -> Mat3 {
+        target.from_xyz() * bradford(self.white(), target.white()) * self.to_xyz()
+    }
+
+    pub fn convert(self, target: ColorSpace, color: Color) -> Color {
+        self.conversion(target) * color
+    }
+
+    /// `film` converted into `target` and labelled as being in it.
+    pub fn convert_film(self, target: ColorSpace, film: &Film) -> Film {
+        if self == target {
+            return film.clone().in_color_space(target);
+        }
+        let matrix = self.conversion(target);
+        film.map(|color| matrix * color).in_color_space(target)
+    }
+
+    /// Matrix in this space that renders a surface lit by an illuminant of `temperature`
+    /// kelvin as neutral, like a camera's white balance setting.
+    pub fn white_balance(self, temperature: f64) -> Mat3 {
+        self.from_xyz() * bradford(white_point(temperature), self.white()) * self.to_xyz()
+    }
+}
+
+/// XYZ of the chromaticity `xy` with unit luminance.
+pub fn xy_to_xyz((x, y): (f64, f64)) -> Vec3 {
+    Vec3::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+/// Bradford chromatic adaptation in XYZ from white point `source` to `target`.
+pub fn bradford(source: (f64, f64), target: (f64, f64)) -> Mat3 {
+    const CONE: Mat3 = Mat3::new([
+        [0.8951, 0.2664, -0.1614],
+        [-0.7502, 1.7135, 0.0367],
+        [0.0389, -0.0685, 1.0296]
+    ]);
+    let source = CONE * xy_to_xyz(source);
+    let target = CONE * xy_to_xyz(target);
+    let gain = Mat3::diagonal(Vec3::new(target.x / source.x, target.y / source.y, target.z / source.z));
+    CONE.inverse() * gain * CONE
+}
+
+/// Color temperatures in kelvin that [`white_point`] covers.
+pub const TEMPERATURE_RANGE: RangeInclusive<f64> = 1667.0..=25000.0;
+
+/// Chromaticity of a white illuminant of correlated color `temperature` in kelvin: the CIE
+/// daylight locus from 4000 K upwards and the Planckian locus below it. Panics outside
+/// [`TEMPERATURE_RANGE`].
+pub fn white_point(temperature: f64) -> (f64, f64) {
+    if !TEMPERATURE_RANGE.contains(&temperature) { panic!("Color temperature must be between 1667 and 25000 K") };
+    let t = temperature;
+    let (t2, t3) = (t * t, t * t * t);
+    if t >= 4000.0 {
+        let x = if t <= 7000.0 {
+            -4.6070e9 / t3 + 2.9678e6 / t2 + 0.09911e3 / t + 0.244063
+        } else {
+            -2.0064e9 / t3 + 1.9018e6 / t2 + 0.24748e3 / t + 0.237040
+        };
+        (x, -3.0 * x * x + 2.870 * x - 0.275)
+    } else {
+        let x = -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910;
+        let y = if t <= 2222.0 {
+            -1.1063814 * x * x * x - 1.34811020 * x * x + 2.18555832 * x - 0.20219683
+        } else {
+            -0.9549476 * x * x * x - 1.37418593 * x * x + 2.09137015 * x - 0.16748867
+        };
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_mat_near(actual: Mat3, expected: [[f64; 3]; 3], tolerance: f64) {
+        for (actual_row, expected_row) in actual.rows.iter().zip(expected) {
+            for (value, expected) in actual_row.iter().zip(expected_row) {
+                assert!((value - expected).abs() < tolerance, "{:?}", actual);
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_to_xyz() {
+        assert_mat_near(ColorSpace::LinearSrgb.to_xyz(), [
+            [0.4124, 0.3576, 0.1805],
+            [0.2126, 0.7152, 0.0722],
+            [0.0193, 0.1192, 0.9505]
+        ], 1e-4);
+    }
+
+    #[test]
+    fn conversions() {
+        assert_mat_near(ColorSpace::LinearSrgb.conversion(ColorSpace::Rec2020), [
+            [0.6274, 0.3293, 0.0433],
+            [0.0691, 0.9195, 0.0114],
+            [0.0164, 0.0880, 0.8956]
+        ], 1e-3);
+        assert_mat_near(ColorSpace::LinearSrgb.conversion(ColorSpace::AcesCg), [
+            [0.6131, 0.3395, 0.0474],
+            [0.0702, 0.9164, 0.0135],
+            [0.0206, 0.1096, 0.8698]
+        ], 1e-3);
+
+        let white = ColorSpace::AcesCg.convert(ColorSpace::LinearSrgb, Color::ones());
+        assert!((white - Color::ones()).length() < 1e-9);
+
+        let film = Film::from_colors(1, 1, vec![Color::ones()]);
+        assert_eq!(ColorSpace::LinearSrgb.convert_film(ColorSpace::Rec2020, &film).color_space(), ColorSpace::Rec2020);
+    }
+
+    #[test]
+    fn white_balance() {
+        let (x, y) = white_point(6504.0);
+        assert!((x - D65.0).abs() < 1e-3 && (y - D65.1).abs() < 1e-3);
+
+        let warm = ColorSpace::LinearSrgb.white_balance(3200.0);
+        let balanced = warm * Color::ones();
+        assert!(balanced.z > balanced.y && balanced.y > balanced.x);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::tile::Tile;
+use crate::camera::CameraError;
+use crate::error::{Error, Result};
+use std::result;
+
+/// A rectangle of the image, from (`x0`, `y0`) inclusive to (`x1`, `y1`) exclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Pixels { x0: usize, y0: usize, x1: usize, y1: usize },
+    /// Corners as fractions of the image width and height.
+    Normalized { x0: f64, y0: f64, x1: f64, y1: f64 }
+}
+
+impl Region {
+    /// The pixels of a `width` by `height` image inside this region.
+    pub fn to_tile(&self, width: usize, height: usize) -> Tile {
+        self.try_to_tile(width, height).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_to_tile(&self, width: usize, height: usize) -> result::Result<Tile, CameraError> {
+        let (x0, y0, x1, y1) = match *self {
+            Region::Pixels { x0, y0, x1, y1 } => (x0, y0, x1, y1),
+            Region::Normalized { x0, y0, x1, y1 } => {
+                let scale = |value: f64, size: usize| (value.clamp(0.0, 1.0) * size as f64).round() as usize;
+                (scale(x0, width), scale(y0, height), scale(x1, width), scale(y1, height))
+            }
+        };
+        let tile = Tile { x0: x0.min(width), y0: y0.min(height), x1: x1.min(width), y1: y1.min(height) };
+        if tile.x0 >= tile.x1 || tile.y0 >= tile.y1 { return Err(CameraError::EmptyRegion) };
+        Ok(tile)
+    }
+}
+
+/// Restricts rendering to `region`. Pixels inside it come out exactly as in a full render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CropWindow {
+    pub region: Region,
+    /// Whether the output keeps the full image size, black outside the region, rather than
+    /// covering just the region.
+    pub full_frame: bool
+}
+
+impl CropWindow {
+    pub fn new(region: Region, full_frame: bool) -> CropWindow {
+        CropWindow { region, full_frame }
+    }
+}
+
+/// Takes `sample_multiplier` times the usual samples inside `region`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionOfInterest {
+    pub region: Region,
+    pub sample_multiplier: u32
+}
+
+impl RegionOfInterest {
+    pub fn new(region: Region, sample_multiplier: u32) -> RegionOfInterest {
+        Self::try_new(region, sample_multiplier).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(region: Region, sample_multiplier: u32) -> Result<RegionOfInterest> {
+        if sample_multiplier == 0 { return Err(Error::invalid_value("Sample multiplier", "must be at least one")) };
+        Ok(RegionOfInterest { region, sample_multiplier })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_to_tiles() {
+        let pixels = Region::Pixels { x0: 2, y0: 1, x1: 50, y1: 3 };
+        assert_eq!(pixels.to_tile(10, 10), Tile { x0: 2, y0: 1, x1: 10, y1: 3 });
+
+        let normalized = Region::Normalized { x0: 0.25, y0: 0.5, x1: 0.75, y1: 1.0 };
+        assert_eq!(normalized.to_tile(8, 4), Tile { x0: 2, y0: 2, x1: 6, y1: 4 });
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_region() {
+        Region::Pixels { x0: 4, y0: 0, x1: 4, y1: 2 }.to_tile(10, 10);
+    }
+}
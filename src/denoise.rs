@@ -0,0 +1,156 @@
+use crate::aov::Aovs;
+use crate::color::{Color, luminance};
+use crate::film::Film;
+use crate::error::{Error, Result};
+use rayon::prelude::*;
+
+/// Edge-avoiding à-trous wavelet denoiser. Each pass blurs with a B3 spline kernel whose taps
+/// spread twice as far as the previous pass's, weighting every tap by how closely its color
+/// and feature buffers match the center pixel's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    pub iterations: u32,
+    /// Tolerance for color differences, relative to the center pixel's luminance. Halves with
+    /// every pass.
+    pub color_sigma: f64,
+    pub normal_sigma: f64,
+    pub albedo_sigma: f64,
+    /// Tolerance for depth differences, relative to the center pixel's depth and the tap distance.
+    pub depth_sigma: f64
+}
+
+/// Most passes a denoiser takes. The last one's taps are already 512 pixels apart.
+pub const MAX_ITERATIONS: u32 = 10;
+
+impl Denoiser {
+    pub fn new(iterations: u32, color_sigma: f64, normal_sigma: f64, albedo_sigma: f64, depth_sigma: f64) -> Denoiser {
+        Self::try_new(iterations, color_sigma, normal_sigma, albedo_sigma, depth_sigma).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(iterations: u32, color_sigma: f64, normal_sigma: f64, albedo_sigma: f64, depth_sigma: f64) -> Result<Denoiser> {
+        if !(1..=MAX_ITERATIONS).contains(&iterations) { return Err(Error::invalid_value("Denoiser iterations", "must be between one and ten")) };
+        if color_sigma <= 0.0 || normal_sigma <= 0.0 || albedo_sigma <= 0.0 || depth_sigma <= 0.0 {
+            return Err(Error::invalid_value("Denoiser tolerances", "must be positive"));
+        };
+        Ok(Denoiser { iterations, color_sigma, normal_sigma, albedo_sigma, depth_sigma })
+    }
+
+    /// Denoises `film`, guided by its AOV layers when it has them and by color alone otherwise.
+    /// With AOVs, lighting is filtered separately from albedo so texture detail stays sharp.
+    pub fn denoise(&self, film: &Film) -> Film {
+        let aovs = film.aovs();
+        let (width, height) = (film.width, film.height);
+        let albedo = |index: usize| aovs.as_ref().map_or(Color::ones(), |aovs| demodulation_albedo(aovs.albedo[index]));
+
+        let mut colors: Vec<Color> = film.pixels().enumerate().map(|(index, color)| demodulate(color, albedo(index))).collect();
+        for iteration in 0..self.iterations.min(MAX_ITERATIONS) {
+            let step = 1usize << iteration;
+            let color_sigma = self.color_sigma / step as f64;
+            colors = (0..width * height)
+                .into_par_iter()
+                .map(|index| self.filter_pixel(&colors, aovs.as_ref(), width, height, index, step, color_sigma))
+                .collect();
+        }
+        film.with_colors(colors.into_iter().enumerate().map(|(index, color)| albedo(index) * color).collect())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn filter_pixel(
+        &self,
+        colors: &[Color],
+        aovs: Option<&Aovs>,
+        width: usize,
+        height: usize,
+        index: usize,
+        step: usize,
+        color_sigma: f64
+    ) -> Color {
+        const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+        let (x, y) = ((index % width) as isize, (index / width) as isize);
+        let center = colors[index];
+        let color_scale = color_sigma * color_sigma * (luminance(center).abs() + 1e-2).powi(2);
+
+        let mut sum = Color::zeroes();
+        let mut total = 0.0;
+        for (dy, ky) in KERNEL.iter().enumerate() {
+            for (dx, kx) in KERNEL.iter().enumerate() {
+                let qx = x + (dx as isize - 2) * step as isize;
+                let qy = y + (dy as isize - 2) * step as isize;
+                if qx < 0 || qy < 0 || qx >= width as isize || qy >= height as isize {
+                    continue;
+                }
+                let q = qy as usize * width + qx as usize;
+                let mut exponent = (colors[q] - center).length_squared() / color_scale;
+                if let Some(aovs) = aovs {
+                    exponent += (aovs.normal[q] - aovs.normal[index]).length_squared() / self.normal_sigma.powi(2);
+                    exponent += (aovs.albedo[q] - aovs.albedo[index]).length_squared() / self.albedo_sigma.powi(2);
+                    let depth_scale = self.depth_sigma * step as f64 * aovs.depth[index].max(1e-6);
+                    exponent += (aovs.depth[q] - aovs.depth[index]).abs() / depth_scale;
+                }
+                let weight = kx * ky * (-exponent).exp();
+                sum += weight * colors[q];
+                total += weight;
+            }
+        }
+        sum / total
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser::new(5, 1.0, 0.3, 0.1, 0.1)
+    }
+}
+
+/// Albedo to divide lighting by, kept away from zero so dark surfaces do not blow up.
+fn demodulation_albedo(albedo: Color) -> Color {
+    Color::new(albedo.x.max(1e-2), albedo.y.max(1e-2), albedo.z.max(1e-2))
+}
+
+fn demodulate(color: Color, albedo: Color) -> Color {
+    Color::new(color.x / albedo.x, color.y / albedo.y, color.z / albedo.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aov::AovSample;
+    use crate::filter::Filter;
+    use crate::utils::random_double;
+    use crate::vec3::Vec3;
+
+    fn variance(colors: &[Color]) -> f64 {
+        let mean = colors.iter().map(|c| c.x).sum::<f64>() / colors.len() as f64;
+        colors.iter().map(|c| (c.x - mean).powi(2)).sum::<f64>() / colors.len() as f64
+    }
+
+    #[test]
+    fn smooths_noise() {
+        let colors: Vec<Color> = (0..32 * 32).map(|_| (0.5 + 0.2 * (random_double() - 0.5)) * Color::ones()).collect();
+        let film = Film::from_colors(32, 32, colors.clone());
+        let denoised = Denoiser::default().denoise(&film).to_colors();
+        assert!(variance(&denoised) < 0.1 * variance(&colors));
+    }
+
+    #[test]
+    fn keeps_feature_edges() {
+        let mut film = Film::new(16, 1).with_aovs();
+        for x in 0..16 {
+            let (color, normal) = if x < 8 { (0.2, Vec3::new(0.0, 1.0, 0.0)) } else { (0.8, Vec3::new(1.0, 0.0, 0.0)) };
+            let aov = AovSample { albedo: Color::ones(), normal, depth: 1.0, object_id: 1, background: Color::zeroes(), sky: Color::ones() };
+            film.splat_sample(&Filter::box_filter(), x as f64 + 0.5, 0.5, color * Color::ones(), Some(&aov));
+        }
+        let denoised = Denoiser::default().denoise(&film);
+        assert!((denoised.pixel(7, 0).x - 0.2).abs() < 1e-3);
+        assert!((denoised.pixel(8, 0).x - 0.8).abs() < 1e-3);
+        assert!(denoised.aovs().is_some());
+    }
+
+    #[test]
+    fn bounds_iterations() {
+        assert!(Denoiser::try_new(MAX_ITERATIONS + 1, 1.0, 1.0, 1.0, 1.0).is_err());
+        let film = Film::from_colors(2, 1, vec![Color::ones(), Color::zeroes()]);
+        let denoiser = Denoiser { iterations: 40, ..Denoiser::default() };
+        assert_eq!(denoiser.denoise(&film).to_colors().len(), 2);
+    }
+}
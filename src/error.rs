@@ -0,0 +1,84 @@
+use crate::camera::CameraError;
+use crate::scene_file::SceneFileError;
+use std::fmt;
+use std::io;
+
+/// Errors from configuring scenes, rendering them and writing the images.
+#[derive(Debug)]
+pub enum Error {
+    Camera(CameraError),
+    /// `parameter` was given a value that does not satisfy `requirement`.
+    InvalidValue { parameter: &'static str, requirement: &'static str },
+    /// Every problem found in a scene file.
+    SceneFile(Vec<SceneFileError>),
+    Io(io::Error)
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn invalid_value(parameter: &'static str, requirement: &'static str) -> Error {
+        Error::InvalidValue { parameter, requirement }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Camera(err) => err.fmt(f),
+            Error::InvalidValue { parameter, requirement } => write!(f, "{} {}", parameter, requirement),
+            Error::SceneFile(errors) => {
+                let lines: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            Error::Io(err) => err.fmt(f)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Camera(err) => Some(err),
+            Error::InvalidValue { .. } | Error::SceneFile(_) => None,
+            Error::Io(err) => Some(err)
+        }
+    }
+}
+
+impl From<CameraError> for Error {
+    fn from(err: CameraError) -> Self {
+        Error::Camera(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::geometry::Sphere;
+    use crate::material::{Dielectric, Lambertian, Metal};
+    use crate::vec3::Point3;
+    use std::sync::Arc;
+
+    #[test]
+    fn messages() {
+        assert_eq!(Error::invalid_value("Sphere radius", "must not be negative").to_string(), "Sphere radius must not be negative");
+        assert_eq!(Error::from(CameraError::ZeroImageSize).to_string(), CameraError::ZeroImageSize.to_string());
+    }
+
+    #[test]
+    fn fallible_constructors() {
+        let material = Arc::new(Lambertian::new(Color::ones()));
+        assert!(Sphere::try_new(Point3::zeroes(), 1.0, material.clone()).is_ok());
+        assert!(matches!(Sphere::try_new(Point3::zeroes(), -1.0, material), Err(Error::InvalidValue { parameter: "Sphere radius", .. })));
+        assert!(Metal::try_new(Color::ones(), 1.5).is_err());
+        assert!(Dielectric::try_new(0.0).is_err());
+    }
+}
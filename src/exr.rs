@@ -0,0 +1,265 @@
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use half::f16;
+use std::io::{self, Write};
+
+/// Storage type of every channel in an OpenEXR file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExrPixelType {
+    #[default]
+    Half,
+    Float
+}
+
+impl ExrPixelType {
+    fn id(self) -> i32 {
+        match self {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2
+        }
+    }
+}
+
+/// Scanline compression of an OpenEXR file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExrCompression {
+    None,
+    /// Deflate over blocks of 16 scanlines.
+    #[default]
+    Zip
+}
+
+impl ExrCompression {
+    fn lines_per_block(self) -> usize {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => 16
+        }
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3
+        }
+    }
+}
+
+/// One named image channel, such as `R` or `albedo.G`, in scanline order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExrChannel {
+    pub name: String,
+    pub values: Vec<f32>,
+    /// Storage type of this channel, instead of the image's.
+    pub pixel_type: Option<ExrPixelType>
+}
+
+impl ExrChannel {
+    pub fn new(name: impl Into<String>, values: Vec<f32>) -> ExrChannel {
+        ExrChannel { name: name.into(), values, pixel_type: None }
+    }
+
+    /// This channel stored as `pixel_type` whatever the image's type, such as for IDs that
+    /// halves cannot hold exactly.
+    pub fn stored_as(self, pixel_type: ExrPixelType) -> ExrChannel {
+        ExrChannel { pixel_type: Some(pixel_type), ..self }
+    }
+}
+
+/// Writes `channels` as a single-part scanline OpenEXR image of `width` by `height` pixels, with
+/// `chromaticities` giving the red, green and blue primaries and the white point of its colors.
+pub fn write_exr(
+    stream: &mut dyn Write,
+    width: usize,
+    height: usize,
+    channels: &[ExrChannel],
+    chromaticities: [(f64, f64); 4],
+    pixel_type: ExrPixelType,
+    compression: ExrCompression
+) -> io::Result<()> {
+    if channels.iter().any(|channel| channel.values.len() != width * height) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Every EXR channel needs one value per pixel"));
+    }
+
+    let mut channels: Vec<&ExrChannel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.name.as_bytes().cmp(b.name.as_bytes()));
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut channel_list = Vec::new();
+    for channel in &channels {
+        channel_list.extend_from_slice(channel.name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&channel.pixel_type.unwrap_or(pixel_type).id().to_le_bytes());
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+
+    let chromaticities: Vec<u8> = chromaticities
+        .iter()
+        .flat_map(|&(x, y)| [x as f32, y as f32])
+        .flat_map(f32::to_le_bytes)
+        .collect();
+
+    attribute(&mut header, "channels", "chlist", &channel_list);
+    attribute(&mut header, "chromaticities", "chromaticities", &chromaticities);
+    attribute(&mut header, "compression", "compression", &[compression.id()]);
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut header, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(&mut header, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+
+    let lines_per_block = compression.lines_per_block();
+    let blocks: Vec<Vec<u8>> = (0..height.div_ceil(lines_per_block))
+        .map(|block| {
+            let y0 = block * lines_per_block;
+            let y1 = usize::min(y0 + lines_per_block, height);
+            let mut data = Vec::new();
+            for y in y0..y1 {
+                for channel in &channels {
+                    for &value in &channel.values[y * width..(y + 1) * width] {
+                        match channel.pixel_type.unwrap_or(pixel_type) {
+                            ExrPixelType::Half => data.extend_from_slice(&f16::from_f32(value).to_le_bytes()),
+                            ExrPixelType::Float => data.extend_from_slice(&value.to_le_bytes())
+                        }
+                    }
+                }
+            }
+            match compression {
+                ExrCompression::None => Ok(data),
+                ExrCompression::Zip => zip_block(data)
+            }
+        })
+        .collect::<io::Result<_>>()?;
+
+    let mut offset = (header.len() + 8 * blocks.len()) as u64;
+    for block in &blocks {
+        header.extend_from_slice(&offset.to_le_bytes());
+        offset += 8 + block.len() as u64;
+    }
+    stream.write_all(&header)?;
+
+    for (index, block) in blocks.iter().enumerate() {
+        stream.write_all(&((index * lines_per_block) as i32).to_le_bytes())?;
+        stream.write_all(&(block.len() as i32).to_le_bytes())?;
+        stream.write_all(block)?;
+    }
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Interleaves the bytes of `data` into halves, delta-encodes them and deflates the result as
+/// OpenEXR's ZIP compression expects. Blocks that do not shrink are stored uncompressed.
+fn zip_block(data: Vec<u8>) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&zip_predict(&data))?;
+    let compressed = encoder.finish()?;
+    Ok(if compressed.len() < data.len() { compressed } else { data })
+}
+
+fn zip_predict(data: &[u8]) -> Vec<u8> {
+    let mut reordered: Vec<u8> = data.iter().step_by(2).chain(data.iter().skip(1).step_by(2)).copied().collect();
+    for i in (1..reordered.len()).rev() {
+        reordered[i] = reordered[i].wrapping_sub(reordered[i - 1]).wrapping_add(128);
+    }
+    reordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    fn zip_unpredict(data: &[u8]) -> Vec<u8> {
+        let mut reordered = data.to_vec();
+        for i in 1..reordered.len() {
+            reordered[i] = reordered[i - 1].wrapping_add(reordered[i]).wrapping_sub(128);
+        }
+        let half = reordered.len().div_ceil(2);
+        (0..reordered.len())
+            .map(|i| if i % 2 == 0 { reordered[i / 2] } else { reordered[half + i / 2] })
+            .collect()
+    }
+
+    const SRGB: [(f64, f64); 4] = [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06), (0.3127, 0.3290)];
+
+    fn channels() -> Vec<ExrChannel> {
+        vec![
+            ExrChannel::new("R", vec![1.0, 2.5]),
+            ExrChannel::new("G", vec![0.0, -1.0]),
+            ExrChannel::new("B", vec![100.0, 0.25])
+        ]
+    }
+
+    #[test]
+    fn uncompressed_float() {
+        let mut out = Vec::new();
+        write_exr(&mut out, 2, 1, &channels(), SRGB, ExrPixelType::Float, ExrCompression::None).unwrap();
+        assert_eq!(&out[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+        let pixels: Vec<u8> = [100.0f32, 0.25, 0.0, -1.0, 1.0, 2.5].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(&out[out.len() - pixels.len()..], pixels.as_slice());
+        let size = i32::from_le_bytes(out[out.len() - pixels.len() - 4..out.len() - pixels.len()].try_into().unwrap());
+        assert_eq!(size, 24);
+    }
+
+    #[test]
+    fn chromaticities() {
+        let mut out = Vec::new();
+        write_exr(&mut out, 2, 1, &channels(), SRGB, ExrPixelType::Half, ExrCompression::None).unwrap();
+        let name = b"chromaticities\0chromaticities\0\x20\0\0\0";
+        let start = out.windows(name.len()).position(|window| window == name).unwrap() + name.len();
+        let values: Vec<f32> = out[start..start + 32].chunks(4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap())).collect();
+        assert_eq!(values, [0.64, 0.33, 0.30, 0.60, 0.15, 0.06, 0.3127, 0.3290]);
+    }
+
+    #[test]
+    fn channels_keep_their_own_type() {
+        let mut out = Vec::new();
+        let channels = [ExrChannel::new("Y", vec![0.5]), ExrChannel::new("id", vec![4097.0]).stored_as(ExrPixelType::Float)];
+        write_exr(&mut out, 1, 1, &channels, SRGB, ExrPixelType::Half, ExrCompression::None).unwrap();
+
+        let pixels: Vec<u8> = f16::from_f32(0.5).to_le_bytes().into_iter().chain(4097f32.to_le_bytes()).collect();
+        assert_eq!(&out[out.len() - pixels.len()..], pixels.as_slice());
+    }
+
+    #[test]
+    fn zip_round_trip() {
+        let data: Vec<u8> = (0..1000u32).flat_map(|v| f16::from_f32(v as f32 * 0.01).to_le_bytes()).collect();
+        assert_eq!(zip_unpredict(&zip_predict(&data)), data);
+
+        let compressed = zip_block(data.clone()).unwrap();
+        assert!(compressed.len() < data.len());
+        let mut decoded = Vec::new();
+        ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut decoded).unwrap();
+        assert_eq!(zip_unpredict(&decoded), data);
+    }
+
+    #[test]
+    fn rejects_mismatched_channels() {
+        let mut out = Vec::new();
+        let result = write_exr(&mut out, 3, 1, &channels(), SRGB, ExrPixelType::Half, ExrCompression::Zip);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
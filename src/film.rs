@@ -0,0 +1,251 @@
+use crate::aov::{AovBuffer, AovSample, Aovs};
+use crate::color::Color;
+use crate::colorspace::ColorSpace;
+use crate::filter::Filter;
+
+/// Linear RGB image accumulated from weighted samples, covering the `width` by `height`
+/// pixels starting at (`x0`, `y0`) of the full image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film {
+    pub x0: usize,
+    pub y0: usize,
+    pub width: usize,
+    pub height: usize,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+    aovs: Option<AovBuffer>,
+    color_space: ColorSpace
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Film {
+        Film::with_origin(0, 0, width, height)
+    }
+
+    pub fn with_origin(x0: usize, y0: usize, width: usize, height: usize) -> Film {
+        Film {
+            x0,
+            y0,
+            width,
+            height,
+            sums: vec![Color::zeroes(); width * height],
+            weights: vec![0.0; width * height],
+            aovs: None,
+            color_space: ColorSpace::default()
+        }
+    }
+
+    /// Also accumulates AOV layers from the samples splatted into this film.
+    pub fn with_aovs(mut self) -> Film {
+        self.aovs = Some(AovBuffer::new(self.width * self.height));
+        self
+    }
+
+    /// A film holding `colors` in scanline order, each with unit weight.
+    pub fn from_colors(width: usize, height: usize, colors: Vec<Color>) -> Film {
+        if colors.len() != width * height { panic!("Film needs exactly one color per pixel") };
+        Film {
+            x0: 0,
+            y0: 0,
+            width,
+            height,
+            sums: colors,
+            weights: vec![1.0; width * height],
+            aovs: None,
+            color_space: ColorSpace::default()
+        }
+    }
+
+    /// Weight-normalized color of the pixel at (`i`, `j`) relative to the film's origin.
+    pub fn pixel(&self, i: usize, j: usize) -> Color {
+        let index = j * self.width + i;
+        Self::normalize(self.sums[index], self.weights[index])
+    }
+
+    /// Total filter weight accumulated by the pixel at (`i`, `j`).
+    pub fn weight(&self, i: usize, j: usize) -> f64 {
+        self.weights[j * self.width + i]
+    }
+
+    pub fn set_pixel(&mut self, i: usize, j: usize, color: Color) {
+        let index = j * self.width + i;
+        self.sums[index] = color;
+        self.weights[index] = 1.0;
+    }
+
+    /// Weight-normalized pixel colors in scanline order.
+    pub fn pixels(&self) -> impl Iterator<Item = Color> + '_ {
+        self.sums.iter().zip(&self.weights).map(|(&sum, &weight)| Self::normalize(sum, weight))
+    }
+
+    pub fn to_colors(&self) -> Vec<Color> {
+        self.pixels().collect()
+    }
+
+    /// A film of the same extent and weights with `f` applied to every resolved pixel color.
+    /// AOV layers are carried over unchanged.
+    pub fn map(&self, f: impl Fn(Color) -> Color) -> Film {
+        self.with_colors(self.pixels().map(f).collect())
+    }
+
+    /// A film of the same extent, weights and AOV layers whose pixels resolve to `colors`, given
+    /// in scanline order.
+    pub fn with_colors(&self, colors: Vec<Color>) -> Film {
+        if colors.len() != self.width * self.height { panic!("Film needs exactly one color per pixel") };
+        let sums = colors.into_iter().zip(&self.weights).map(|(color, &weight)| weight * color).collect();
+        Film {
+            x0: self.x0,
+            y0: self.y0,
+            width: self.width,
+            height: self.height,
+            sums,
+            weights: self.weights.clone(),
+            aovs: self.aovs.clone(),
+            color_space: self.color_space
+        }
+    }
+
+    /// Color space of the film's linear colors, linear sRGB unless labelled otherwise.
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// This film labelled as holding colors in `color_space`, without converting them.
+    pub fn in_color_space(mut self, color_space: ColorSpace) -> Film {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Weight-normalized AOV layers, if this film accumulates them.
+    pub fn aovs(&self) -> Option<Aovs> {
+        self.aovs.as_ref().map(|aovs| aovs.resolve(self.width, self.height, &self.weights))
+    }
+
+    fn normalize(sum: Color, weight: f64) -> Color {
+        if weight.abs() > 1e-12 { sum / weight } else { Color::zeroes() }
+    }
+
+    /// Adds a sample at continuous image position (`x`, `y`) to the pixels in this buffer that
+    /// `filter` reaches.
+    pub fn splat(&mut self, filter: &Filter, x: f64, y: f64, color: Color) {
+        self.splat_sample(filter, x, y, color, None);
+    }
+
+    /// Like `splat`, also adding `aov` to the AOV layers if this film accumulates them.
+    pub fn splat_sample(&mut self, filter: &Filter, x: f64, y: f64, color: Color, aov: Option<&AovSample>) {
+        let radius = filter.radius();
+        let x0 = ((x - 0.5 - radius).ceil().max(0.0) as usize).max(self.x0);
+        let x1 = ((x - 0.5 + radius).ceil().max(0.0) as usize).min(self.x0 + self.width);
+        let y0 = ((y - 0.5 - radius).ceil().max(0.0) as usize).max(self.y0);
+        let y1 = ((y - 0.5 + radius).ceil().max(0.0) as usize).min(self.y0 + self.height);
+
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let weight = filter.evaluate(px as f64 + 0.5 - x, py as f64 + 0.5 - y);
+                if weight != 0.0 {
+                    let index = (py - self.y0) * self.width + (px - self.x0);
+                    self.sums[index] += weight * color;
+                    self.weights[index] += weight;
+                    if let (Some(aovs), Some(aov)) = (&mut self.aovs, aov) {
+                        aovs.add(index, weight, aov);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The part of this film covering the `width` by `height` pixels starting at (`x0`, `y0`)
+    /// of the full image, which is empty wherever this film does not reach.
+    pub fn cropped(&self, x0: usize, y0: usize, width: usize, height: usize) -> Film {
+        let mut cropped = Film::with_origin(x0, y0, width, height).in_color_space(self.color_space);
+        if self.aovs.is_some() {
+            cropped = cropped.with_aovs();
+        }
+        cropped.merge(self);
+        cropped
+    }
+
+    /// Adds the overlapping pixels of `other` into this buffer.
+    pub fn merge(&mut self, other: &Film) {
+        let (x0, x1) = (usize::max(self.x0, other.x0), usize::min(self.x0 + self.width, other.x0 + other.width));
+        let (y0, y1) = (usize::max(self.y0, other.y0), usize::min(self.y0 + self.height, other.y0 + other.height));
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let dst = (y - self.y0) * self.width + (x - self.x0);
+                let src = (y - other.y0) * other.width + (x - other.x0);
+                self.sums[dst] += other.sums[src];
+                self.weights[dst] += other.weights[src];
+                if let (Some(aovs), Some(other_aovs)) = (&mut self.aovs, &other.aovs) {
+                    aovs.merge_pixel(dst, other_aovs, src);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_splats_into_one_pixel() {
+        let mut buffer = Film::new(3, 3);
+        buffer.splat(&Filter::box_filter(), 1.2, 1.7, Color::ones());
+        let pixels = buffer.to_colors();
+        for (index, pixel) in pixels.iter().enumerate() {
+            let expected = if index == 4 { Color::ones() } else { Color::zeroes() };
+            assert_eq!(*pixel, expected);
+        }
+    }
+
+    #[test]
+    fn tent_splats_into_neighbors() {
+        let mut buffer = Film::new(3, 3);
+        buffer.splat(&Filter::tent(), 1.5, 1.5, Color::ones());
+        buffer.splat(&Filter::tent(), 1.0, 1.5, Color::zeroes());
+        let pixels = buffer.to_colors();
+        assert!((pixels[4].x - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(pixels[3], Color::zeroes());
+        assert_eq!(pixels[5], Color::zeroes());
+    }
+
+    #[test]
+    fn merge_overlapping_pixels() {
+        let mut image = Film::new(2, 3);
+        let mut tile = Film::with_origin(1, 1, 2, 3);
+        tile.splat(&Filter::box_filter(), 1.5, 2.5, Color::ones());
+        image.merge(&tile);
+        let pixels = image.to_colors();
+        assert_eq!(pixels[5], Color::ones());
+        assert!(pixels[..5].iter().all(|&pixel| pixel == Color::zeroes()));
+    }
+
+    #[test]
+    fn from_colors() {
+        let mut film = Film::from_colors(2, 1, vec![Color::ones(), Color::zeroes()]);
+        film.set_pixel(1, 0, Color::new(0.5, 0.5, 0.5));
+        assert_eq!(film.pixel(0, 0), Color::ones());
+        assert_eq!(film.weight(1, 0), 1.0);
+        assert_eq!(film.to_colors(), vec![Color::ones(), Color::new(0.5, 0.5, 0.5)]);
+    }
+
+    #[test]
+    fn map_keeps_weights() {
+        let mut film = Film::new(2, 1);
+        film.splat(&Filter::box_filter(), 0.5, 0.5, Color::ones());
+        film.splat(&Filter::box_filter(), 0.5, 0.5, Color::ones());
+        let mapped = film.map(|color| 0.5 * color);
+        assert_eq!(mapped.pixel(0, 0), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(mapped.weight(0, 0), 2.0);
+        assert_eq!(mapped.weight(1, 0), 0.0);
+    }
+
+    #[test]
+    fn cropped() {
+        let mut film = Film::new(3, 2);
+        film.splat(&Filter::box_filter(), 1.5, 1.5, Color::ones());
+        let cropped = film.cropped(1, 1, 2, 1);
+        assert_eq!((cropped.x0, cropped.y0), (1, 1));
+        assert_eq!(cropped.to_colors(), vec![Color::ones(), Color::zeroes()]);
+    }
+}
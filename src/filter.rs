@@ -0,0 +1,124 @@
+use std::f64::consts::PI;
+
+/// Pixel reconstruction filter. Each sample is splatted into every pixel whose center lies
+/// within `radius` of it, weighted by the separable filter value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box { radius: f64 },
+    Tent { radius: f64 },
+    Gaussian { radius: f64, alpha: f64 },
+    Mitchell { radius: f64, b: f64, c: f64 },
+    Lanczos { radius: f64, tau: f64 }
+}
+
+impl Filter {
+    pub const fn box_filter() -> Filter {
+        Filter::Box { radius: 0.5 }
+    }
+
+    pub const fn tent() -> Filter {
+        Filter::Tent { radius: 1.0 }
+    }
+
+    pub const fn gaussian() -> Filter {
+        Filter::Gaussian { radius: 1.5, alpha: 2.0 }
+    }
+
+    pub const fn mitchell() -> Filter {
+        Filter::Mitchell { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 }
+    }
+
+    pub const fn lanczos() -> Filter {
+        Filter::Lanczos { radius: 3.0, tau: 3.0 }
+    }
+
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius, .. } => radius
+        }
+    }
+
+    /// The first parameter that is not finite and positive, as the filter needs, if any.
+    pub fn invalid_parameter(&self) -> Option<&'static str> {
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        match *self {
+            _ if !positive(self.radius()) => Some("radius"),
+            Filter::Gaussian { alpha, .. } if !positive(alpha) => Some("alpha"),
+            Filter::Lanczos { tau, .. } if !positive(tau) => Some("tau"),
+            _ => None
+        }
+    }
+
+    /// Number of neighboring pixels on each side a sample can reach.
+    pub fn pixel_reach(&self) -> usize {
+        (self.radius() - 0.5).max(0.0).ceil() as usize
+    }
+
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match *self {
+            Filter::Box { radius } => if x <= radius { 1.0 } else { 0.0 },
+            Filter::Tent { radius } => f64::max(radius - x, 0.0),
+            Filter::Gaussian { radius, alpha } => {
+                f64::max((-alpha * x * x).exp() - (-alpha * radius * radius).exp(), 0.0)
+            },
+            Filter::Mitchell { radius, b, c } => {
+                let x = 2.0 * x / radius;
+                if x > 2.0 {
+                    0.0
+                } else if x > 1.0 {
+                    ((-b - 6.0 * c) * x.powi(3) + (6.0 * b + 30.0 * c) * x.powi(2)
+                        + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3) + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                        + (6.0 - 2.0 * b)) / 6.0
+                }
+            },
+            Filter::Lanczos { radius, tau } => {
+                if x > radius { 0.0 } else { sinc(x) * sinc(x / tau) }
+            }
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::box_filter()
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x < 1e-5 { 1.0 } else { (PI * x).sin() / (PI * x) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_shapes() {
+        assert!(Filter::mitchell().evaluate(2.0, 0.0).abs() < 1e-12);
+        assert_eq!(Filter::mitchell().evaluate(2.5, 0.0), 0.0);
+        assert!(Filter::mitchell().evaluate(0.0, 0.0) > 0.0);
+        assert!(Filter::lanczos().evaluate(1.5, 0.0) < 0.0);
+        assert_eq!(Filter::gaussian().evaluate(1.5, 0.0), 0.0);
+        assert_eq!(Filter::tent().evaluate(0.5, 0.5), 0.25);
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        assert_eq!(Filter::lanczos().invalid_parameter(), None);
+        assert_eq!(Filter::Box { radius: -1.0 }.invalid_parameter(), Some("radius"));
+        assert_eq!(Filter::Tent { radius: f64::NAN }.invalid_parameter(), Some("radius"));
+        assert_eq!(Filter::Gaussian { radius: 1.5, alpha: 0.0 }.invalid_parameter(), Some("alpha"));
+        assert_eq!(Filter::Lanczos { radius: 3.0, tau: 0.0 }.invalid_parameter(), Some("tau"));
+    }
+}
@@ -2,6 +2,8 @@ use crate::vec3::{Vec3, Point3};
 use crate::ray::Ray;
 use crate::hittable::{Hittable, HitRecord};
 use crate::material::Material;
+use crate::error::{Error, Result};
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 pub struct Sphere {
@@ -12,8 +14,20 @@ pub struct Sphere {
 
 impl Sphere { 
     pub fn new(center: Point3, radius: f64, mat: Arc<dyn Material>) -> Sphere {
-        if radius < 0.0 { panic!("Sphere cannot have negative radius") };
-        Sphere { center, radius, mat }
+        Self::try_new(center, radius, mat).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(center: Point3, radius: f64, mat: Arc<dyn Material>) -> Result<Sphere> {
+        if radius < 0.0 || radius.is_nan() { return Err(Error::invalid_value("Sphere radius", "must not be negative")) };
+        Ok(Sphere { center, radius, mat })
+    }
+
+    /// Longitude and latitude of the point `p` on the unit sphere, as fractions. `u` runs around
+    /// the y axis from -x, and `v` from the bottom pole to the top.
+    fn uv(p: Point3) -> (f64, f64) {
+        let theta = (-p.y).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
     }
 }
 
@@ -40,12 +54,17 @@ impl Hittable for Sphere {
 
         let p = r.at(root);
         let outward_normal = (p - self.center) / self.radius; 
+        let (u, v) = Self::uv(outward_normal);
 
         Some(HitRecord {
             p,
             normal: outward_normal,
             t: root,
+            u,
+            v,
             front_face: true,
+            vertex_color: None,
+            object_id: 0,
             mat: self.mat.clone()
         }.set_face_normal(r, &outward_normal))
     }
@@ -56,6 +75,12 @@ pub struct Scene {
 
 }
 
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Scene {
     pub fn new() -> Scene {
         Scene { objects: vec![] }
@@ -75,11 +100,11 @@ impl Hittable for Scene {
         let mut closest = bounds.1;
         let mut rec: Option<HitRecord> = None;
 
-        for object in &self.objects {
+        for (index, object) in self.objects.iter().enumerate() {
             let hit = object.hit(r, (bounds.0, closest));
             if let Some(t) = hit {
                 closest = t.t;
-                rec = Some(t);
+                rec = Some(HitRecord { object_id: index as u32 + 1, ..t });
             } 
         }
         rec
@@ -1,3 +1,4 @@
+use crate::color::Color;
 use crate::vec3::{Vec3, Point3};
 use crate::ray::Ray;
 use crate::material::Material;
@@ -8,7 +9,14 @@ pub struct HitRecord {
     pub normal: Vec3,
     pub mat: Arc<dyn Material>,
     pub t: f64,
-    pub front_face: bool
+    /// Surface coordinates of the hit, each in [0, 1].
+    pub u: f64,
+    pub v: f64,
+    pub front_face: bool,
+    /// Color interpolated from the vertices of a mesh that has them, tinting the material.
+    pub vertex_color: Option<Color>,
+    /// Identifies the scene object that was hit, starting from one.
+    pub object_id: u32
 }
 
 impl HitRecord {
@@ -20,6 +28,11 @@ impl HitRecord {
             ..self
         } 
     }
+
+    /// `albedo` tinted by the vertex color at the hit, if there is one.
+    pub fn tint(&self, albedo: Color) -> Color {
+        self.vertex_color.map_or(albedo, |color| color * albedo)
+    }
 }
 
 pub trait Hittable: Send + Sync {
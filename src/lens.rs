@@ -0,0 +1,440 @@
+use crate::color::luminance;
+use crate::film::Film;
+use crate::ray::Ray;
+use crate::utils::random_double;
+use crate::vec3::Vec3;
+use crate::error::{Error, Result};
+use std::f64::consts::PI;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// World units per millimeter, taking world units as meters.
+pub const MILLIMETER: f64 = 0.001;
+
+/// Shape of the lens opening, which shapes the bokeh of out-of-focus highlights.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Aperture {
+    #[default]
+    Circle,
+    /// Regular polygon formed by `blades` straight blades, rotated by `rotation` degrees.
+    Polygon { blades: u32, rotation: f64 },
+    /// Arbitrary shape given by an image.
+    Mask(Arc<ApertureMask>)
+}
+
+impl Aperture {
+    pub fn polygon(blades: u32, rotation: f64) -> Aperture {
+        Self::try_polygon(blades, rotation).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_polygon(blades: u32, rotation: f64) -> Result<Aperture> {
+        if blades < 3 { return Err(Error::invalid_value("Aperture blades", "must be at least three")) };
+        Ok(Aperture::Polygon { blades, rotation })
+    }
+
+    /// A uniformly distributed point of the aperture, scaled to fit the unit disk.
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            Aperture::Circle => Vec3::random_disk(),
+            Aperture::Polygon { blades, rotation } => {
+                let wedge = ((random_double() * *blades as f64) as u32).min(blades.saturating_sub(1));
+                let angle = |k: u32| rotation.to_radians() + 2.0 * PI * k as f64 / *blades as f64;
+                let a = Vec3::new(angle(wedge).cos(), angle(wedge).sin(), 0.0);
+                let b = Vec3::new(angle(wedge + 1).cos(), angle(wedge + 1).sin(), 0.0);
+                let (mut s, mut t) = (random_double(), random_double());
+                if s + t > 1.0 {
+                    (s, t) = (1.0 - s, 1.0 - t);
+                }
+                s * a + t * b
+            }
+            Aperture::Mask(mask) => mask.sample()
+        }
+    }
+}
+
+/// Aperture shape from an image, where brighter pixels let through more light.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    cdf: Vec<f64>
+}
+
+impl ApertureMask {
+    /// A mask from non-negative `weights` in scanline order.
+    pub fn new(width: usize, height: usize, weights: Vec<f64>) -> ApertureMask {
+        Self::try_new(width, height, weights).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(width: usize, height: usize, weights: Vec<f64>) -> Result<ApertureMask> {
+        if weights.len() != width * height { return Err(Error::invalid_value("Aperture mask", "needs exactly one weight per pixel")) };
+        if weights.iter().any(|&weight| weight.is_nan() || weight < 0.0) {
+            return Err(Error::invalid_value("Aperture mask weights", "must not be negative"));
+        };
+        let mut total = 0.0;
+        let mut cdf: Vec<f64> = weights.iter().map(|&weight| { total += weight; total }).collect();
+        if total <= 0.0 || !total.is_finite() { return Err(Error::invalid_value("Aperture mask", "must have a finite, non-zero total weight")) };
+        cdf.iter_mut().for_each(|value| *value /= total);
+        Ok(ApertureMask { width, height, cdf })
+    }
+
+    /// A mask weighted by the luminance of each pixel of `film`.
+    pub fn from_film(film: &Film) -> ApertureMask {
+        Self::try_from_film(film).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_from_film(film: &Film) -> Result<ApertureMask> {
+        let weights = film.pixels().map(|color| luminance(color).max(0.0)).collect();
+        ApertureMask::try_new(film.width, film.height, weights)
+    }
+
+    /// Reads a mask from a PNG, weighting each pixel by its average color channel.
+    pub fn load(path: &Path) -> io::Result<ApertureMask> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+        let (channels, color_channels) = match info.color_type {
+            png::ColorType::Grayscale => (1, 1),
+            png::ColorType::GrayscaleAlpha => (2, 1),
+            png::ColorType::Rgb => (3, 3),
+            _ => (4, 3)
+        };
+        let weights: Vec<f64> = data[..info.buffer_size()]
+            .chunks(channels)
+            .map(|pixel| pixel[..color_channels].iter().map(|&value| value as f64).sum::<f64>() / (255.0 * color_channels as f64))
+            .collect();
+        if weights.iter().all(|&weight| weight == 0.0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Aperture mask {} is black", path.display())));
+        }
+        ApertureMask::try_new(info.width as usize, info.height as usize, weights)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("Aperture mask {}: {}", path.display(), err)))
+    }
+
+    /// A point drawn in proportion to the mask's weights, with the longer side of the mask
+    /// spanning [-1, 1].
+    pub fn sample(&self) -> Vec3 {
+        let u = random_double();
+        let index = self.cdf.partition_point(|&value| value <= u).min(self.cdf.len() - 1);
+        let size = usize::max(self.width, self.height) as f64;
+        let x = (index % self.width) as f64 + random_double() - 0.5 * self.width as f64;
+        let y = (index / self.width) as f64 + random_double() - 0.5 * self.height as f64;
+        Vec3::new(2.0 * x / size, -2.0 * y / size, 0.0)
+    }
+}
+
+/// Optics that set the camera's field of view and depth of field.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Lens {
+    /// The camera's `vfov` and `defocus_angle`.
+    #[default]
+    Angles,
+    /// Ideal thin lens of `focal_length` millimeters at `f_stop`, in front of a sensor
+    /// `sensor_height` millimeters tall.
+    Thin { focal_length: f64, f_stop: f64, sensor_height: f64 },
+    /// Traces rays through the elements of `system`, focused at the camera's `focus_dist`,
+    /// onto a sensor `sensor_height` millimeters tall.
+    Realistic { system: Arc<LensSystem>, sensor_height: f64 }
+}
+
+impl Lens {
+    pub fn thin(focal_length: f64, f_stop: f64, sensor_height: f64) -> Lens {
+        Self::try_thin(focal_length, f_stop, sensor_height).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_thin(focal_length: f64, f_stop: f64, sensor_height: f64) -> Result<Lens> {
+        if focal_length <= 0.0 || f_stop <= 0.0 || sensor_height <= 0.0 {
+            return Err(Error::invalid_value("Focal length, f-stop and sensor height", "must be positive"));
+        };
+        Ok(Lens::Thin { focal_length, f_stop, sensor_height })
+    }
+
+    /// Vertical field of view in degrees, given the camera's `vfov`. Realistic lenses have none.
+    pub fn vfov(&self, vfov: f64) -> f64 {
+        match *self {
+            Lens::Thin { focal_length, sensor_height, .. } => 2.0 * (sensor_height / (2.0 * focal_length)).atan().to_degrees(),
+            _ => vfov
+        }
+    }
+
+    /// Radius of the lens disk rays start from, given the camera's `defocus_angle` and `focus_dist`.
+    pub fn defocus_radius(&self, defocus_angle: f64, focus_dist: f64) -> f64 {
+        match *self {
+            Lens::Angles => focus_dist * f64::tan((defocus_angle / 2.0).to_radians()),
+            Lens::Thin { focal_length, f_stop, .. } => 0.5 * focal_length * MILLIMETER / f_stop,
+            Lens::Realistic { .. } => 0.0
+        }
+    }
+}
+
+/// One spherical or flat surface of a lens system, with lengths in world units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LensElement {
+    /// Radius of curvature, positive when the surface bulges toward the scene. Zero marks the
+    /// aperture stop.
+    pub curvature_radius: f64,
+    /// Distance along the axis to the next surface toward the film.
+    pub thickness: f64,
+    /// Index of refraction of the medium behind the surface, or zero for the aperture stop.
+    pub eta: f64,
+    pub aperture_radius: f64
+}
+
+impl LensElement {
+    pub fn is_stop(&self) -> bool {
+        self.curvature_radius == 0.0
+    }
+}
+
+/// Lens elements listed from the front of the lens to the back. The thickness of the last
+/// element is the distance from the rear surface to the film.
+///
+/// Rays are traced in lens space, with the film in the z = 0 plane and the lens toward
+/// negative z, so x, y and z line up with the camera's `u`, `v` and `w`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LensSystem {
+    pub elements: Vec<LensElement>
+}
+
+impl LensSystem {
+    pub fn new(elements: Vec<LensElement>) -> LensSystem {
+        Self::try_new(elements).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(elements: Vec<LensElement>) -> Result<LensSystem> {
+        if elements.is_empty() { return Err(Error::invalid_value("Lens system", "needs at least one element")) };
+        Ok(LensSystem { elements })
+    }
+
+    /// Parses a lens prescription in the tabular format used by pbrt: one surface per line
+    /// as radius of curvature, thickness, index of refraction and aperture diameter, all in
+    /// millimeters, with `#` starting a comment.
+    pub fn parse(text: &str) -> io::Result<LensSystem> {
+        let error = |line: usize, message: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line + 1, message));
+        let mut elements = Vec::new();
+        for (line, content) in text.lines().enumerate() {
+            let content = content.split('#').next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+            let values = content
+                .split_whitespace()
+                .map(|value| value.parse::<f64>().map_err(|_| error(line, format!("'{}' is not a number", value))))
+                .collect::<io::Result<Vec<f64>>>()?;
+            let [curvature_radius, thickness, eta, aperture_diameter] = values[..] else {
+                return Err(error(line, format!("expected 4 values but found {}", values.len())));
+            };
+            if thickness < 0.0 || aperture_diameter <= 0.0 || eta < 0.0 {
+                return Err(error(line, "thickness, index of refraction and aperture must not be negative".to_string()));
+            }
+            elements.push(LensElement {
+                curvature_radius: curvature_radius * MILLIMETER,
+                thickness: thickness * MILLIMETER,
+                eta,
+                aperture_radius: 0.5 * aperture_diameter * MILLIMETER
+            });
+        }
+        if elements.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Lens prescription has no elements"));
+        }
+        Ok(LensSystem { elements })
+    }
+
+    pub fn load(path: &Path) -> io::Result<LensSystem> {
+        let text = fs::read_to_string(path)?;
+        LensSystem::parse(&text).map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+    }
+
+    /// The rear element, through which rays leave the film.
+    pub fn rear(&self) -> &LensElement {
+        self.elements.last().unwrap()
+    }
+
+    /// Distance from the film to the front surface.
+    pub fn length(&self) -> f64 {
+        self.elements.iter().map(|element| element.thickness).sum()
+    }
+
+    /// Follows `ray`, starting on the film side, through every element out into the scene.
+    /// `None` if an element's rim blocks it or it is totally internally reflected.
+    pub fn trace_from_film(&self, ray: Ray) -> Option<Ray> {
+        let mut ray = ray;
+        let mut z = 0.0;
+        for (index, element) in self.elements.iter().enumerate().rev() {
+            z -= element.thickness;
+            let eta_t = if index > 0 && self.elements[index - 1].eta != 0.0 { self.elements[index - 1].eta } else { 1.0 };
+            ray = Self::cross_surface(ray, element, z, element.eta, eta_t)?;
+        }
+        Some(ray)
+    }
+
+    /// Follows `ray`, starting in the scene, through every element toward the film.
+    pub fn trace_from_scene(&self, ray: Ray) -> Option<Ray> {
+        let mut ray = ray;
+        let mut z = -self.length();
+        for (index, element) in self.elements.iter().enumerate() {
+            let eta_i = if index > 0 && self.elements[index - 1].eta != 0.0 { self.elements[index - 1].eta } else { 1.0 };
+            let eta_t = if element.eta != 0.0 { element.eta } else { 1.0 };
+            ray = Self::cross_surface(ray, element, z, eta_i, eta_t)?;
+            z += element.thickness;
+        }
+        Some(ray)
+    }
+
+    /// Moves `ray` onto the surface of `element`, whose vertex is at `z`, and refracts it from
+    /// a medium of index `eta_i` into one of `eta_t`.
+    fn cross_surface(ray: Ray, element: &LensElement, z: f64, eta_i: f64, eta_t: f64) -> Option<Ray> {
+        let direction = ray.direction.unit();
+        if element.is_stop() {
+            let t = (z - ray.origin.z) / direction.z;
+            let p = ray.origin + t * direction;
+            return (t >= 0.0 && p.x * p.x + p.y * p.y <= element.aperture_radius.powi(2)).then(|| Ray::new(p, direction));
+        }
+
+        let radius = element.curvature_radius;
+        let center = Vec3::new(0.0, 0.0, z + radius);
+        let oc = ray.origin - center;
+        let b = Vec3::dot(oc, direction);
+        let discriminant = b * b - (oc.length_squared() - radius * radius);
+        if discriminant < 0.0 {
+            return None;
+        }
+        let (t0, t1) = (-b - discriminant.sqrt(), -b + discriminant.sqrt());
+        let t = if (direction.z > 0.0) != (radius < 0.0) { t0.min(t1) } else { t0.max(t1) };
+        if t < 0.0 {
+            return None;
+        }
+        let p = ray.origin + t * direction;
+        if p.x * p.x + p.y * p.y > element.aperture_radius.powi(2) {
+            return None;
+        }
+
+        let mut normal = (p - center).unit();
+        if Vec3::dot(normal, direction) > 0.0 {
+            normal = -normal;
+        }
+        let eta = eta_i / eta_t;
+        let cos_theta = -Vec3::dot(direction, normal);
+        if eta * eta * (1.0 - cos_theta * cos_theta) > 1.0 {
+            return None;
+        }
+        Some(Ray::new(p, Vec3::refract(direction, normal, eta)))
+    }
+
+    /// Axial positions of the focal point and principal plane on the far side of a ray
+    /// entering parallel to the axis at height `x` and leaving as `out`.
+    fn cardinal_points(x: f64, out: &Ray) -> (f64, f64) {
+        let t_focus = -out.origin.x / out.direction.x;
+        let t_principal = (x - out.origin.x) / out.direction.x;
+        (out.origin.z + t_focus * out.direction.z, out.origin.z + t_principal * out.direction.z)
+    }
+
+    /// Focal length and the principal planes on the scene and film sides, from tracing rays
+    /// parallel to the axis near it.
+    fn thick_lens(&self) -> Option<(f64, f64, f64)> {
+        let x = 0.01 * self.elements.last()?.aperture_radius.min(self.elements[0].aperture_radius);
+        let from_scene = self.trace_from_scene(Ray::new(Vec3::new(x, 0.0, -self.length() - 1.0), Vec3::new(0.0, 0.0, 1.0)))?;
+        let (film_focus, film_principal) = Self::cardinal_points(x, &from_scene);
+        let from_film = self.trace_from_film(Ray::new(Vec3::new(x, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0)))?;
+        let (_, scene_principal) = Self::cardinal_points(x, &from_film);
+        Some((film_focus - film_principal, scene_principal, film_principal))
+    }
+
+    /// Effective focal length in world units.
+    pub fn focal_length(&self) -> Option<f64> {
+        self.thick_lens().map(|(focal_length, _, _)| focal_length)
+    }
+
+    /// A copy with the film moved so that objects `focus_dist` in front of it are in focus,
+    /// using a thick lens approximation. `None` if the lens cannot focus that close or has no
+    /// elements.
+    pub fn focused(&self, focus_dist: f64) -> Option<LensSystem> {
+        let (focal_length, scene_principal, film_principal) = self.thick_lens()?;
+        let a = scene_principal + focus_dist;
+        let b = film_principal;
+        let c = (a - b) * (a - b - 4.0 * focal_length);
+        if focal_length <= 0.0 || c < 0.0 {
+            return None;
+        }
+        let shift = 0.5 * (a + b - c.sqrt());
+        let mut focused = self.clone();
+        focused.elements.last_mut().unwrap().thickness += shift;
+        (focused.rear().thickness > 0.0).then_some(focused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINGLET: &str = "
+        # radius thickness ior aperture
+        50.0  5.0  1.5  20.0
+        -50.0 45.0 1.0  20.0
+    ";
+
+    #[test]
+    fn polygon_samples_stay_inside() {
+        assert!(Aperture::try_polygon(2, 0.0).is_err());
+        let aperture = Aperture::polygon(6, 0.0);
+        let apothem = (PI / 6.0).cos();
+        for _ in 0..1000 {
+            let p = aperture.sample();
+            let angle = p.y.atan2(p.x).rem_euclid(PI / 3.0) - PI / 6.0;
+            assert!(p.length() * angle.cos() <= apothem + 1e-12);
+        }
+    }
+
+    #[test]
+    fn mask_samples_bright_pixels() {
+        assert!(ApertureMask::try_new(2, 1, vec![0.0]).is_err());
+        assert!(ApertureMask::try_new(2, 1, vec![0.0, -1.0]).is_err());
+        assert!(ApertureMask::try_from_film(&Film::new(2, 1)).is_err());
+        let mask = ApertureMask::new(2, 1, vec![0.0, 1.0]);
+        for _ in 0..100 {
+            let p = mask.sample();
+            assert!(p.x >= 0.0 && p.x <= 1.0 && p.y.abs() <= 0.5);
+        }
+    }
+
+    #[test]
+    fn thin_lens_angles() {
+        let lens = Lens::thin(50.0, 2.0, 24.0);
+        assert!((lens.vfov(0.0) - 26.9915).abs() < 1e-3);
+        assert!((lens.defocus_radius(0.0, 10.0) - 0.0125).abs() < 1e-12);
+    }
+
+    #[test]
+    fn parse_prescription() {
+        let system = LensSystem::parse(SINGLET).unwrap();
+        assert_eq!(system.elements.len(), 2);
+        assert_eq!(system.elements[1].curvature_radius, -0.05);
+        assert_eq!(system.rear().aperture_radius, 0.01);
+
+        let err = LensSystem::parse("50 5 1.5 20\n50 5 x 20").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: 'x' is not a number");
+        assert!(LensSystem::parse("50 5 1.5").is_err());
+        assert!(LensSystem::try_new(Vec::new()).is_err());
+        assert!(LensSystem { elements: Vec::new() }.focused(2.0).is_none());
+    }
+
+    #[test]
+    fn focuses_at_distance() {
+        let system = LensSystem::parse(SINGLET).unwrap().focused(2.0).unwrap();
+        let rear_z = -system.rear().thickness;
+        let axis_crossings: Vec<f64> = [0.01, -0.01]
+            .iter()
+            .map(|&height| {
+                let target = Vec3::new(height * system.rear().aperture_radius, 0.0, rear_z);
+                let out = system.trace_from_film(Ray::new(Vec3::zeroes(), target)).unwrap();
+                out.origin.z - out.origin.x / out.direction.x * out.direction.z
+            })
+            .collect();
+        for z in axis_crossings {
+            assert!((z + 2.0).abs() < 1e-3, "crosses the axis at {}", z);
+        }
+    }
+}
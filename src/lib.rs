@@ -0,0 +1,34 @@
+pub mod camera;
+pub mod hittable;
+pub mod geometry;
+pub mod vec3;
+pub mod ray;
+pub mod color;
+pub mod utils;
+pub mod material;
+pub mod adaptive;
+pub mod progressive;
+pub mod filter;
+pub mod tile;
+pub mod film;
+pub mod aov;
+pub mod output;
+pub mod exr;
+pub mod mat3;
+pub mod tonemap;
+pub mod colorspace;
+pub mod denoise;
+pub mod postprocess;
+pub mod projection;
+pub mod stereo;
+pub mod lens;
+pub mod crop;
+pub mod error;
+pub mod scenes;
+pub mod texture;
+pub mod transform;
+pub mod scene_file;
+pub mod mesh;
+pub mod pbrt;
+pub mod ply;
+pub mod stl;
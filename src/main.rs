@@ -1,80 +1,420 @@
-mod camera;
-mod hittable;
-mod geometry;
-mod vec3;
-mod ray;
-mod color;
-mod utils;
-mod material;
-
-use camera::Camera;
-use geometry::{Scene, Sphere};
-use vec3::{Vec3, Point3};
-use color::Color;
-use material::{Lambertian, Metal, Dielectric};
-use std::io::{stdout, BufWriter};
-use std::sync::Arc;
-use utils::{random_double, random_range};
-
-
-fn main() {
-    let mut world = Scene::new();
-
-    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
-    world.add(Box::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_material)));
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let choose_mat = random_double();
-            let center = Point3::new(a as f64 + 0.9 * random_double(), 0.2, b as f64 + 0.9 * random_double());
-
-            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                match choose_mat {
-                    mat if mat < 0.8 => {
-                        let albedo = Color::random() * Color::random();
-                        let sphere_material = Arc::new(Lambertian::new(albedo));
-                        world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
-                    },
-                    mat if mat < 0.95 => {
-                        let albedo = Color::random_range(0.5, 1.0);
-                        let fuzz = random_range(0.0, 0.5);
-                        let sphere_material = Arc::new(Metal::new(albedo, fuzz));
-                        world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
-                    }
-                    _ => {
-                        let sphere_material = Arc::new(Dielectric::new(1.5));
-                        world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
-                    }
-                }
+use raytracer::camera::CameraBuilder;
+use raytracer::geometry::Scene;
+use raytracer::vec3::{Vec3, Point3};
+use raytracer::film::Film;
+use raytracer::output::{format_for, save_as, save_aov_siblings, write_image, ImageFormat};
+use raytracer::exr::{ExrCompression, ExrPixelType};
+use raytracer::colorspace::ColorSpace;
+use raytracer::denoise::Denoiser;
+use raytracer::postprocess::{Effect, PostProcess};
+use raytracer::progressive::{overwrite_image, Progressive};
+use raytracer::tonemap::{ToneMapping, Tonemapper};
+use raytracer::error::{Error, Result};
+use raytracer::{pbrt, scene_file, scenes};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::io::{self, stdout, BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Renders the scenes of Ray Tracing in One Weekend.
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    render: RenderArgs
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a scene (the default when no subcommand is given).
+    Render(Box<RenderArgs>),
+    /// List the built-in scenes.
+    Scenes
+}
+
+#[derive(Args)]
+struct RenderArgs {
+    /// Built-in scene to render.
+    #[arg(short, long, value_enum, default_value_t = SceneChoice::RandomSpheres)]
+    scene: SceneChoice,
+
+    /// Scene file to render instead of a built-in scene: TOML, or pbrt when it ends in .pbrt.
+    #[arg(long, value_name = "FILE", conflicts_with = "scene")]
+    scene_file: Option<PathBuf>,
+
+    /// Image to write. Without one, an ASCII PPM is written to stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Image format, instead of guessing it from the output file's extension.
+    #[arg(short, long, value_enum)]
+    format: Option<FormatChoice>,
+
+    /// Image width in pixels; the height follows from the aspect ratio.
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "height")]
+    width: Option<u32>,
+
+    /// Image height in pixels; the width follows from the aspect ratio.
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..))]
+    height: Option<u32>,
+
+    /// Width over height, as a number or as W:H.
+    #[arg(long, value_name = "RATIO", value_parser = parse_aspect_ratio)]
+    aspect_ratio: Option<f64>,
+
+    /// Samples per pixel.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    spp: Option<u32>,
+
+    /// Maximum number of ray bounces.
+    #[arg(long, value_name = "N")]
+    max_depth: Option<u32>,
+
+    #[command(flatten)]
+    camera: CameraArgs,
+
+    /// Worker threads [default: one per CPU].
+    #[arg(short = 'j', long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    threads: Option<u32>,
+
+    /// Seed for the scene layout and every sample, so renders repeat exactly [default: the
+    /// scene file's seed, or 0].
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Curve mapping HDR color into the displayable range. HDR formats are never tonemapped.
+    #[arg(long, value_enum, default_value_t = TonemapperChoice::Clamp)]
+    tonemapper: TonemapperChoice,
+
+    /// Exposure adjustment in stops, applied before tonemapping.
+    #[arg(long, value_name = "EV", default_value_t = 0.0, allow_negative_numbers = true)]
+    exposure: f64,
+
+    /// Color space of the written pixels.
+    #[arg(long, value_enum, default_value_t = ColorSpaceChoice::LinearSrgb)]
+    color_space: ColorSpaceChoice,
+
+    /// Denoise the image, guided by albedo, normal and depth AOVs.
+    #[arg(long)]
+    denoise: bool,
+
+    /// Post-processing effects to apply in order, separated by commas.
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "EFFECTS")]
+    post: Vec<EffectChoice>,
+
+    /// Also write the albedo, normal, depth and object ID AOVs and the background and sky light
+    /// groups next to the output image.
+    #[arg(long, requires = "output")]
+    aovs: bool,
+
+    /// Render in passes of doubling sample counts, overwriting the output image after each.
+    #[arg(long, requires = "output")]
+    progressive: bool
+}
+
+/// Overrides of the scene's camera.
+#[derive(Args)]
+struct CameraArgs {
+    /// Camera position, as X,Y,Z.
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vec3, allow_hyphen_values = true)]
+    lookfrom: Option<Point3>,
+
+    /// Point the camera looks at, as X,Y,Z.
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vec3, allow_hyphen_values = true)]
+    lookat: Option<Point3>,
+
+    /// Camera-relative up direction, as X,Y,Z.
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vec3, allow_hyphen_values = true)]
+    vup: Option<Vec3>,
+
+    /// Vertical field of view in degrees.
+    #[arg(long, value_name = "DEGREES")]
+    vfov: Option<f64>,
+
+    /// Cone angle in degrees of the rays through each pixel; zero turns depth of field off.
+    #[arg(long, value_name = "DEGREES")]
+    defocus_angle: Option<f64>,
+
+    /// Distance to the plane of perfect focus.
+    #[arg(long, value_name = "DISTANCE")]
+    focus_dist: Option<f64>
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SceneChoice {
+    /// The book's final scene of random small spheres around three large ones.
+    RandomSpheres,
+    /// A diffuse, a hollow glass and a fuzzy metal sphere with strong defocus blur.
+    ThreeSpheres
+}
+
+impl SceneChoice {
+    fn build(self, seed: u64) -> (Scene, CameraBuilder) {
+        match self {
+            SceneChoice::RandomSpheres => scenes::random_spheres(seed),
+            SceneChoice::ThreeSpheres => scenes::three_spheres()
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatChoice {
+    /// ASCII P3 PPM.
+    PpmAscii,
+    /// Binary P6 PPM.
+    Ppm,
+    /// Binary P5 PGM of the luminance.
+    Pgm,
+    /// 8-bit PNG.
+    Png,
+    /// 16-bit PNG.
+    Png16,
+    /// Half-float OpenEXR.
+    Exr,
+    /// Full-float OpenEXR.
+    Exr32,
+    /// Radiance RGBE.
+    Hdr,
+    /// Portable float map.
+    Pfm
+}
+
+impl From<FormatChoice> for ImageFormat {
+    fn from(choice: FormatChoice) -> Self {
+        match choice {
+            FormatChoice::PpmAscii => ImageFormat::PpmAscii,
+            FormatChoice::Ppm => ImageFormat::Ppm,
+            FormatChoice::Pgm => ImageFormat::Pgm,
+            FormatChoice::Png => ImageFormat::Png8,
+            FormatChoice::Png16 => ImageFormat::Png16,
+            FormatChoice::Exr => ImageFormat::Exr { pixel_type: ExrPixelType::Half, compression: ExrCompression::Zip },
+            FormatChoice::Exr32 => ImageFormat::Exr { pixel_type: ExrPixelType::Float, compression: ExrCompression::Zip },
+            FormatChoice::Hdr => ImageFormat::Hdr,
+            FormatChoice::Pfm => ImageFormat::Pfm
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TonemapperChoice {
+    /// Clip values above one.
+    Clamp,
+    /// Extended Reinhard, mapping a luminance of 4 to white.
+    Reinhard,
+    /// Hable's filmic curve.
+    Hable,
+    /// Fitted ACES.
+    Aces,
+    /// AgX.
+    Agx
+}
+
+impl From<TonemapperChoice> for Tonemapper {
+    fn from(choice: TonemapperChoice) -> Self {
+        match choice {
+            TonemapperChoice::Clamp => Tonemapper::Clamp,
+            TonemapperChoice::Reinhard => Tonemapper::Reinhard { white: 4.0 },
+            TonemapperChoice::Hable => Tonemapper::Hable,
+            TonemapperChoice::Aces => Tonemapper::Aces,
+            TonemapperChoice::Agx => Tonemapper::AgX
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorSpaceChoice {
+    LinearSrgb,
+    Rec2020,
+    Acescg
+}
+
+impl From<ColorSpaceChoice> for ColorSpace {
+    fn from(choice: ColorSpaceChoice) -> Self {
+        match choice {
+            ColorSpaceChoice::LinearSrgb => ColorSpace::LinearSrgb,
+            ColorSpaceChoice::Rec2020 => ColorSpace::Rec2020,
+            ColorSpaceChoice::Acescg => ColorSpace::AcesCg
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EffectChoice {
+    Bloom,
+    Glare,
+    Vignette,
+    ChromaticAberration,
+    Grain
+}
+
+impl From<EffectChoice> for Effect {
+    fn from(choice: EffectChoice) -> Self {
+        match choice {
+            EffectChoice::Bloom => Effect::bloom(),
+            EffectChoice::Glare => Effect::glare(),
+            EffectChoice::Vignette => Effect::vignette(),
+            EffectChoice::ChromaticAberration => Effect::chromatic_aberration(),
+            EffectChoice::Grain => Effect::grain()
+        }
+    }
+}
+
+fn parse_aspect_ratio(value: &str) -> std::result::Result<f64, String> {
+    let ratio = match value.split_once(':') {
+        Some((width, height)) => {
+            let width: f64 = width.trim().parse().map_err(|_| format!("invalid width in {}", value))?;
+            let height: f64 = height.trim().parse().map_err(|_| format!("invalid height in {}", value))?;
+            width / height
+        }
+        None => value.trim().parse().map_err(|_| format!("expected a number or W:H, found {}", value))?
+    };
+    if ratio > 0.0 && ratio.is_finite() { Ok(ratio) } else { Err("aspect ratio must be positive".to_string()) }
+}
+
+fn parse_vec3(value: &str) -> std::result::Result<Vec3, String> {
+    let components = value
+        .split(',')
+        .map(|component| component.trim().parse::<f64>())
+        .collect::<std::result::Result<Vec<f64>, _>>()
+        .map_err(|_| format!("expected three numbers separated by commas, found {}", value))?;
+    match components[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!("expected three components, found {}", components.len()))
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Some(Command::Render(args)) => render(&args),
+        Some(Command::Scenes) => {
+            list_scenes();
+            Ok(())
+        }
+        None => render(&cli.render)
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn list_scenes() {
+    for scene in SceneChoice::value_variants() {
+        if let Some(value) = scene.to_possible_value() {
+            println!("{:16} {}", value.get_name(), value.get_help().map(|help| help.to_string()).unwrap_or_default());
+        }
+    }
+}
+
+fn render(args: &RenderArgs) -> Result<()> {
+    let format = match (args.format, &args.output) {
+        (Some(format), _) => ImageFormat::from(format),
+        (None, Some(path)) => format_for(path)?,
+        (None, None) => ImageFormat::PpmAscii
+    };
+
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads as usize)
+            .build_global()
+            .map_err(|err| Error::Io(io::Error::other(err)))?;
+    }
+
+    let (world, builder) = match &args.scene_file {
+        Some(path) if path.extension().is_some_and(|extension| extension == "pbrt") => {
+            let (world, builder, warnings) = pbrt::load(path)?;
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
             }
+            (world, builder)
+        }
+        Some(path) => scene_file::load(path)?,
+        None => args.scene.build(args.seed.unwrap_or(0))
+    };
+    let mut cam = configure(builder, args).build()?;
+    if let Some(seed) = args.seed {
+        cam.seed = seed;
+    }
+    cam.aovs = args.denoise || args.aovs;
+
+    let film = match &args.output {
+        Some(path) if args.progressive => {
+            let color_space = cam.color_space;
+            let mut written = Ok(());
+            let film = cam.render_progressive(&world, &Progressive::default(), |film, _| {
+                written = overwrite_image(path, &develop(film, color_space, args, format), format);
+                if written.is_ok() { ControlFlow::Continue(()) } else { ControlFlow::Break(()) }
+            })?;
+            written.map_err(|err| write_error(path, err))?;
+            film
+        }
+        _ => cam.render(&world)?
+    };
+    let film = develop(&film, cam.color_space, args, format);
+
+    match &args.output {
+        Some(path) => {
+            let write = || -> io::Result<()> {
+                save_as(path, &film, format)?;
+                if args.aovs {
+                    save_aov_siblings(path, &film, format)?;
+                }
+                Ok(())
+            };
+            write().map_err(|err| write_error(path, err))?;
         }
+        None => write_stdout(&film, format)?
     }
+    Ok(())
+}
+
+/// The rendered `film`, in `color_space`, denoised, post-processed and made ready for `format`
+/// as `args` ask.
+fn develop(film: &Film, color_space: ColorSpace, args: &RenderArgs, format: ImageFormat) -> Film {
+    let mut film = if args.denoise { Denoiser::default().denoise(film) } else { film.clone() };
+    film = PostProcess::new(args.post.iter().map(|&effect| Effect::from(effect)).collect()).apply(&film);
+    let mut working = color_space;
+    if !format.is_hdr() {
+        // The tonemapping curves are fitted to Rec. 709 primaries, so they run in linear sRGB
+        // before the film is converted to the output space.
+        film = working.convert_film(ColorSpace::LinearSrgb, &film);
+        working = ColorSpace::LinearSrgb;
+        film = ToneMapping::new(args.exposure, args.tonemapper.into()).apply_film(&film);
+    }
+    working.convert_film(args.color_space.into(), &film)
+}
+
+fn write_error(path: &Path, err: io::Error) -> io::Error {
+    io::Error::new(err.kind(), format!("Failed to write image to {}: {}", path.display(), err))
+}
+
+/// `builder` with the resolution, sampling and camera overrides of `args` applied.
+fn configure(mut builder: CameraBuilder, args: &RenderArgs) -> CameraBuilder {
+    let camera = &args.camera;
+    if let Some(aspect_ratio) = args.aspect_ratio { builder = builder.aspect_ratio(aspect_ratio) };
+    if let Some(width) = args.width { builder = builder.image_width(width as usize) };
+    if let Some(height) = args.height { builder = builder.image_height(height as usize) };
+    if let Some(spp) = args.spp { builder = builder.samples_per_pixel(spp) };
+    if let Some(max_depth) = args.max_depth { builder = builder.max_depth(max_depth) };
+    if let Some(lookfrom) = camera.lookfrom { builder = builder.lookfrom(lookfrom) };
+    if let Some(lookat) = camera.lookat { builder = builder.lookat(lookat) };
+    if let Some(vup) = camera.vup { builder = builder.vup(vup) };
+    if let Some(vfov) = camera.vfov { builder = builder.vfov(vfov) };
+    if let Some(defocus_angle) = camera.defocus_angle { builder = builder.defocus_angle(defocus_angle) };
+    if let Some(focus_dist) = camera.focus_dist { builder = builder.focus_dist(focus_dist) };
+    builder
+}
 
-    let material1 = Arc::new(Dielectric::new(1.5));
-    world.add(Box::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, material1)));
-
-    let material2 = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
-    world.add(Box::new(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, material2)));
-
-    let material3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
-    world.add(Box::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, material3)));
-    
-
-    let mut cam = Camera::new(
-        16.0 / 9.0, 
-        720, 
-        100, 
-        50, 
-        20.0, 
-        Point3::new(13.0, 2.0, 3.0), 
-        Point3::new(0.0, 0.0, 0.0), 
-        Vec3::new(0.0, 1.0, 0.0),
-        0.6,
-        10.0
-    );
-    
-    let stdout = stdout().lock();
-    let mut handle = BufWriter::new(stdout);
-    
-    cam.render(&mut handle, &world);
-}
\ No newline at end of file
+fn write_stdout(film: &Film, format: ImageFormat) -> io::Result<()> {
+    let mut handle = BufWriter::new(stdout().lock());
+    write_image(&mut handle, film, format)?;
+    handle.flush()
+}
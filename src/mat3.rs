@@ -0,0 +1,115 @@
+use crate::vec3::Vec3;
+use std::ops::Mul;
+
+/// Row-major 3x3 matrix, used for color space conversions.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Mat3 {
+    pub rows: [[f64; 3]; 3]
+}
+
+impl Mat3 {
+    pub const fn new(rows: [[f64; 3]; 3]) -> Mat3 {
+        Mat3 { rows }
+    }
+
+    pub const fn identity() -> Mat3 {
+        Mat3::new([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    pub const fn diagonal(v: Vec3) -> Mat3 {
+        Mat3::new([[v.x, 0.0, 0.0], [0.0, v.y, 0.0], [0.0, 0.0, v.z]])
+    }
+
+    pub fn transpose(self) -> Mat3 {
+        let m = self.rows;
+        Mat3::new([
+            [m[0][0], m[1][0], m[2][0]],
+            [m[0][1], m[1][1], m[2][1]],
+            [m[0][2], m[1][2], m[2][2]]
+        ])
+    }
+
+    pub fn determinant(self) -> f64 {
+        let m = self.rows;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    pub fn inverse(self) -> Mat3 {
+        let m = self.rows;
+        let det = self.determinant();
+        if det.abs() < 1e-300 { panic!("Matrix is not invertible") };
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+        Mat3::new([
+            [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+            [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+            [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)]
+        ]) * (1.0 / det)
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, v: Vec3) -> Self::Output {
+        let m = self.rows;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z
+        )
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self::Output {
+        let mut rows = [[0.0; 3]; 3];
+        for (r, row) in rows.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.rows[r][k] * other.rows[k][c]).sum();
+            }
+        }
+        Mat3::new(rows)
+    }
+}
+
+impl Mul<f64> for Mat3 {
+    type Output = Self;
+
+    fn mul(self, other: f64) -> Self::Output {
+        Mat3::new(self.rows.map(|row| row.map(|value| value * other)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const M: Mat3 = Mat3::new([[2.0, 0.0, 1.0], [1.0, 3.0, 0.0], [0.0, 1.0, 4.0]]);
+
+    #[test]
+    fn mul_vec() {
+        assert_eq!(M * Vec3::new(1.0, 2.0, 3.0), Vec3::new(5.0, 7.0, 14.0));
+        assert_eq!(Mat3::identity() * Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn inverse() {
+        let product = M * M.inverse();
+        for r in 0..3 {
+            for c in 0..3 {
+                let expected = if r == c { 1.0 } else { 0.0 };
+                assert!((product.rows[r][c] - expected).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn transpose() {
+        assert_eq!(M.transpose().rows[0], [2.0, 1.0, 0.0]);
+        assert_eq!(M.determinant(), 25.0);
+    }
+}
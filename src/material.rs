@@ -2,19 +2,31 @@ use crate::hittable::HitRecord;
 use crate::ray::Ray;
 use crate::color::Color;
 use crate::vec3::Vec3;
+use crate::texture::{SolidColor, Texture};
 use crate::utils::random_double;
+use crate::error::{Error, Result};
+use std::sync::Arc;
 
 pub trait Material: Send + Sync {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool;
+
+    /// Base color at the hit, as written to the albedo AOV.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::ones()
+    }
 }
 
 pub struct Lambertian {
-    albedo: Color
+    texture: Arc<dyn Texture>
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Lambertian {
-        Lambertian { albedo }
+        Lambertian::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(texture: Arc<dyn Texture>) -> Lambertian {
+        Lambertian { texture }
     }
 }
 
@@ -27,10 +39,14 @@ impl Material for Lambertian {
         }
         
         *scattered = Ray::new(rec.p, scatter_direction);
-        *attenuation = self.albedo;
+        *attenuation = self.albedo(rec);
 
         true 
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        rec.tint(self.texture.value(rec.u, rec.v, rec.p))
+    }
 }
 
 pub struct Metal {
@@ -40,8 +56,12 @@ pub struct Metal {
 
 impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Metal {
-        assert!(fuzz <= 1.0, "Fuzz factor cannot be greater than one");
-        Metal { albedo, fuzz }
+        Self::try_new(albedo, fuzz).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(albedo: Color, fuzz: f64) -> Result<Metal> {
+        if !(0.0..=1.0).contains(&fuzz) { return Err(Error::invalid_value("Metal fuzz", "must be between zero and one")) };
+        Ok(Metal { albedo, fuzz })
     }
 }
 
@@ -50,9 +70,13 @@ impl Material for Metal {
         let mut reflected = Vec3::reflect(rin.direction, rec.normal);
         reflected = reflected.unit() + self.fuzz * Vec3::random_unit();
         *scattered = Ray::new(rec.p, reflected);
-        *attenuation = self.albedo;
+        *attenuation = self.albedo(rec);
         Vec3::dot(scattered.direction, rec.normal) > 0.0
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        rec.tint(self.albedo)
+    }
 }
 
 pub struct Dielectric {
@@ -61,7 +85,14 @@ pub struct Dielectric {
 
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Dielectric {
-        Dielectric { refraction_index }
+        Self::try_new(refraction_index).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(refraction_index: f64) -> Result<Dielectric> {
+        if refraction_index <= 0.0 || refraction_index.is_nan() {
+            return Err(Error::invalid_value("Dielectric refraction index", "must be positive"));
+        }
+        Ok(Dielectric { refraction_index })
     }
 
     fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
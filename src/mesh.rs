@@ -0,0 +1,423 @@
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::hittable::{Hittable, HitRecord};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::{Point3, Vec3};
+use std::io;
+use std::sync::Arc;
+
+/// Vertices and triangles as read from a file, before they are placed and given a material.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Option<Vec<Vec3>>,
+    /// Linear per-vertex colors.
+    pub colors: Option<Vec<Color>>,
+    pub triangles: Vec<[u32; 3]>
+}
+
+impl MeshData {
+    /// Moves the vertices and turns the normals by `transform`. Triangles keep their winding,
+    /// so a mirroring transform turns them inside out.
+    pub fn transform(&mut self, transform: &Transform) {
+        for p in &mut self.positions {
+            *p = transform.point(*p);
+        }
+        if let Some(normals) = &mut self.normals {
+            let normal_matrix = transform.linear.inverse().transpose();
+            for n in normals {
+                *n = normal_matrix * *n;
+            }
+        }
+    }
+
+    /// Reverses the winding of every triangle, so that each faces the other way.
+    pub fn flip(&mut self) {
+        for triangle in &mut self.triangles {
+            triangle.swap(1, 2);
+        }
+    }
+
+    pub fn into_mesh(self, mat: Arc<dyn Material>) -> Result<TriangleMesh> {
+        if self.colors.as_ref().is_some_and(|colors| colors.len() != self.positions.len()) {
+            return Err(Error::invalid_value("TriangleMesh colors", "must have one color per vertex"));
+        }
+        let mut mesh = TriangleMesh::try_new(self.positions, self.triangles, self.normals, mat)?;
+        mesh.colors = self.colors;
+        Ok(mesh)
+    }
+}
+
+/// Most entries a mesh reader reserves up front, so that a corrupt count cannot exhaust
+/// memory before any data has been read.
+pub(crate) const MAX_RESERVED: usize = 1 << 24;
+
+/// The error mesh readers give for malformed files.
+pub(crate) fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Triangles sharing vertices and one material. A bounding volume hierarchy over the
+/// triangles means each ray only tests the few near it, so meshes can be very large.
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    /// Per-vertex normals, interpolated across each triangle for smooth shading.
+    normals: Option<Vec<Vec3>>,
+    /// Per-vertex colors, interpolated across each triangle to tint the material.
+    colors: Option<Vec<Color>>,
+    /// Vertex indices of each triangle, in the order of the hierarchy's leaves.
+    triangles: Vec<[u32; 3]>,
+    nodes: Vec<Node>,
+    mat: Arc<dyn Material>
+}
+
+impl TriangleMesh {
+    pub fn new(positions: Vec<Point3>, triangles: Vec<[u32; 3]>, normals: Option<Vec<Vec3>>, mat: Arc<dyn Material>) -> TriangleMesh {
+        Self::try_new(positions, triangles, normals, mat).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(positions: Vec<Point3>, triangles: Vec<[u32; 3]>, normals: Option<Vec<Vec3>>, mat: Arc<dyn Material>) -> Result<TriangleMesh> {
+        if triangles.iter().flatten().any(|&index| index as usize >= positions.len()) {
+            return Err(Error::invalid_value("TriangleMesh indices", "must refer to existing vertices"));
+        }
+        if normals.as_ref().is_some_and(|normals| normals.len() != positions.len()) {
+            return Err(Error::invalid_value("TriangleMesh normals", "must have one normal per vertex"));
+        }
+        let mut mesh = TriangleMesh { positions, normals, colors: None, triangles, nodes: vec![], mat };
+        mesh.build();
+        Ok(mesh)
+    }
+
+    /// Number of triangles.
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    fn vertices(&self, triangle: [u32; 3]) -> [Point3; 3] {
+        triangle.map(|index| self.positions[index as usize])
+    }
+
+    /// Builds the hierarchy by splitting triangles with the surface area heuristic, then
+    /// reorders them so every leaf covers a contiguous run.
+    fn build(&mut self) {
+        let mut primitives: Vec<Primitive> = self
+            .triangles
+            .iter()
+            .enumerate()
+            .map(|(index, &triangle)| {
+                let bounds = Aabb::around(&self.vertices(triangle));
+                Primitive { bounds, centroid: bounds.centroid(), index: index as u32 }
+            })
+            .collect();
+        let mut nodes = Vec::with_capacity(2 * self.triangles.len() / LEAF_SIZE + 1);
+        if !primitives.is_empty() {
+            build_node(&mut nodes, &mut primitives, 0);
+        }
+        self.triangles = primitives.iter().map(|primitive| self.triangles[primitive.index as usize]).collect();
+        self.nodes = nodes;
+    }
+
+    /// Möller-Trumbore intersection, giving the distance and the barycentric coordinates of
+    /// the second and third vertices.
+    fn intersect(&self, r: &Ray, triangle: [u32; 3], bounds: (f64, f64)) -> Option<(f64, f64, f64)> {
+        let [p0, p1, p2] = self.vertices(triangle);
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+        let pvec = Vec3::cross(r.direction, edge2);
+        let det = Vec3::dot(edge1, pvec);
+        if det == 0.0 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let tvec = r.origin - p0;
+        let b1 = Vec3::dot(tvec, pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qvec = Vec3::cross(tvec, edge1);
+        let b2 = Vec3::dot(r.direction, qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = Vec3::dot(edge2, qvec) * inv_det;
+        if t <= bounds.0 || bounds.1 <= t {
+            return None;
+        }
+        Some((t, b1, b2))
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, bounds: (f64, f64)) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_direction = Vec3::new(1.0 / r.direction.x, 1.0 / r.direction.y, 1.0 / r.direction.z);
+        let mut closest: Option<(usize, f64, f64, f64)> = None;
+        let mut far = bounds.1;
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounds.hit(r.origin, inv_direction, (bounds.0, far)) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.offset as usize;
+                for triangle in start..start + node.count as usize {
+                    if let Some((t, b1, b2)) = self.intersect(r, self.triangles[triangle], (bounds.0, far)) {
+                        far = t;
+                        closest = Some((triangle, t, b1, b2));
+                    }
+                }
+            } else if r.direction[node.axis as usize] < 0.0 {
+                // Visit the nearer child first so that the farther one can often be skipped.
+                stack.push(index + 1);
+                stack.push(node.offset as usize);
+            } else {
+                stack.push(node.offset as usize);
+                stack.push(index + 1);
+            }
+        }
+
+        let (triangle, t, b1, b2) = closest?;
+        let triangle = self.triangles[triangle];
+        let [p0, p1, p2] = self.vertices(triangle);
+        let geometric = Vec3::cross(p1 - p0, p2 - p0).unit();
+        let outward_normal = match &self.normals {
+            Some(normals) => {
+                let [n0, n1, n2] = triangle.map(|index| normals[index as usize]);
+                let shading = (1.0 - b1 - b2) * n0 + b1 * n1 + b2 * n2;
+                if shading.near_zero() { geometric } else { shading.unit() }
+            }
+            None => geometric
+        };
+        let vertex_color = self.colors.as_ref().map(|colors| {
+            let [c0, c1, c2] = triangle.map(|index| colors[index as usize]);
+            (1.0 - b1 - b2) * c0 + b1 * c1 + b2 * c2
+        });
+
+        Some(HitRecord {
+            p: r.at(t),
+            normal: outward_normal,
+            t,
+            u: b1,
+            v: b2,
+            front_face: true,
+            vertex_color,
+            object_id: 0,
+            mat: self.mat.clone()
+        }.set_face_normal(r, &outward_normal))
+    }
+}
+
+/// Most triangles in a leaf of the hierarchy.
+const LEAF_SIZE: usize = 4;
+/// Buckets that triangle centroids are sorted into when choosing where to split.
+const BINS: usize = 12;
+
+/// A node of the hierarchy, stored depth first so that the first child directly follows its
+/// parent.
+struct Node {
+    bounds: Aabb,
+    /// First triangle of a leaf, or the index of an interior node's second child.
+    offset: u32,
+    /// Triangles in a leaf, zero for interior nodes.
+    count: u32,
+    /// Axis an interior node splits along.
+    axis: u8
+}
+
+/// A triangle as the hierarchy is built. The triangles themselves are sorted, rather than
+/// indices to them, so that building walks memory in order.
+struct Primitive {
+    bounds: Aabb,
+    centroid: Point3,
+    index: u32
+}
+
+fn build_node(nodes: &mut Vec<Node>, primitives: &mut [Primitive], offset: usize) -> usize {
+    let index = nodes.len();
+    let (node_bounds, centroids) = primitives
+        .iter()
+        .fold((Aabb::empty(), Aabb::empty()), |(bounds, centroids), primitive| (bounds.union(&primitive.bounds), centroids.grow(primitive.centroid)));
+    nodes.push(Node { bounds: node_bounds, offset: offset as u32, count: primitives.len() as u32, axis: 0 });
+    if primitives.len() <= 1 {
+        return index;
+    }
+
+    let extent = centroids.max - centroids.min;
+    let axis = if extent.x > extent.y && extent.x > extent.z { 0 } else if extent.y > extent.z { 1 } else { 2 };
+    if extent[axis] <= 0.0 {
+        return index;
+    }
+    let scale = BINS as f64 / extent[axis];
+    let bin_of = |primitive: &Primitive| (((primitive.centroid[axis] - centroids.min[axis]) * scale) as usize).min(BINS - 1);
+
+    let mut bins = [(0usize, Aabb::empty()); BINS];
+    for primitive in primitives.iter() {
+        let bin = &mut bins[bin_of(primitive)];
+        bin.0 += 1;
+        bin.1 = bin.1.union(&primitive.bounds);
+    }
+    let mut best: Option<(f64, usize)> = None;
+    for split in 1..BINS {
+        let (left_count, left) = bins[..split].iter().fold((0, Aabb::empty()), |(n, acc), (count, b)| (n + count, acc.union(b)));
+        let (right_count, right) = bins[split..].iter().fold((0, Aabb::empty()), |(n, acc), (count, b)| (n + count, acc.union(b)));
+        if left_count == 0 || right_count == 0 {
+            continue;
+        }
+        let cost = left.area() * left_count as f64 + right.area() * right_count as f64;
+        if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+            best = Some((cost, split));
+        }
+    }
+    let Some((cost, split)) = best else { return index };
+    if primitives.len() <= LEAF_SIZE && cost >= node_bounds.area() * primitives.len() as f64 {
+        return index;
+    }
+
+    let mut mid = 0;
+    for j in 0..primitives.len() {
+        if bin_of(&primitives[j]) < split {
+            primitives.swap(mid, j);
+            mid += 1;
+        }
+    }
+    let (left, right) = primitives.split_at_mut(mid);
+    build_node(nodes, left, offset);
+    let second = build_node(nodes, right, offset + mid);
+    nodes[index] = Node { bounds: node_bounds, offset: second as u32, count: 0, axis: axis as u8 };
+    index
+}
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy)]
+struct Aabb {
+    min: Point3,
+    max: Point3
+}
+
+impl Aabb {
+    fn empty() -> Aabb {
+        Aabb { min: Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY), max: Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY) }
+    }
+
+    fn around(points: &[Point3]) -> Aabb {
+        points.iter().fold(Aabb::empty(), |acc, &p| acc.grow(p))
+    }
+
+    fn grow(&self, p: Point3) -> Aabb {
+        Aabb {
+            min: Point3::new(self.min.x.min(p.x), self.min.y.min(p.y), self.min.z.min(p.z)),
+            max: Point3::new(self.max.x.max(p.x), self.max.y.max(p.y), self.max.z.max(p.z))
+        }
+    }
+
+    fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Point3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            max: Point3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z))
+        }
+    }
+
+    fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    fn area(&self) -> f64 {
+        let d = self.max - self.min;
+        if d.x < 0.0 { 0.0 } else { 2.0 * (d.x * d.y + d.y * d.z + d.z * d.x) }
+    }
+
+    /// Whether the ray passes through the box between the distances in `bounds`.
+    fn hit(&self, origin: Point3, inv_direction: Vec3, bounds: (f64, f64)) -> bool {
+        let (mut near, mut far) = bounds;
+        for axis in 0..3 {
+            let t0 = (self.min[axis] - origin[axis]) * inv_direction[axis];
+            let t1 = (self.max[axis] - origin[axis]) * inv_direction[axis];
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            // Written so that NaNs, from rays in the plane of a face, keep the current range.
+            if t0 > near { near = t0 };
+            if t1 < far { far = t1 };
+            if far < near {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn matches_brute_force() {
+        seed_random(7);
+        let mut positions = vec![];
+        let mut triangles = vec![];
+        for _ in 0..500 {
+            let center = Point3::random_range(-5.0, 5.0);
+            let start = positions.len() as u32;
+            for _ in 0..3 {
+                positions.push(center + Vec3::random_range(-0.5, 0.5));
+            }
+            triangles.push([start, start + 1, start + 2]);
+        }
+        let mesh = TriangleMesh::new(positions, triangles.clone(), None, Arc::new(Lambertian::new(Color::ones())));
+        assert_eq!(mesh.len(), 500);
+
+        for _ in 0..500 {
+            let r = Ray::new(Point3::random_range(-8.0, 8.0), Vec3::random_unit());
+            let expected = triangles
+                .iter()
+                .filter_map(|&triangle| mesh.intersect(&r, triangle, (0.001, f64::INFINITY)))
+                .map(|(t, _, _)| t)
+                .fold(f64::INFINITY, f64::min);
+            let found = mesh.hit(&r, (0.001, f64::INFINITY)).map_or(f64::INFINITY, |rec| rec.t);
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn interpolates_normals() {
+        let positions = vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+        let normals = vec![Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0)];
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let mesh = TriangleMesh::new(positions.clone(), vec![[0, 1, 2]], Some(normals), mat.clone());
+
+        let rec = mesh.hit(&Ray::new(Point3::new(0.5, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0)), (0.001, f64::INFINITY)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!(rec.front_face && rec.normal.x > 0.0);
+        assert!(TriangleMesh::try_new(positions, vec![[0, 1, 3]], None, mat).is_err());
+    }
+
+    #[test]
+    fn vertex_colors() {
+        let mut data = MeshData {
+            positions: vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)],
+            normals: None,
+            colors: Some(vec![Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0), Color::new(0.0, 0.0, 1.0)]),
+            triangles: vec![[0, 1, 2]]
+        };
+        data.transform(&Transform::translate(Vec3::new(0.0, 0.0, -1.0)));
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mesh = data.into_mesh(mat.clone()).unwrap();
+
+        let rec = mesh.hit(&Ray::new(Point3::new(0.5, 0.25, 0.0), Vec3::new(0.0, 0.0, -1.0)), (0.001, f64::INFINITY)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!(Arc::ptr_eq(&rec.mat, &mat));
+        assert!((rec.mat.albedo(&rec) - Color::new(0.125, 0.25, 0.125)).length() < 1e-12);
+    }
+}
@@ -0,0 +1,442 @@
+use crate::color::{Color, luminance, write_color, to_bytes, to_words, to_gray_byte, to_linear_bytes, to_linear_words};
+use crate::vec3::Vec3;
+use crate::exr::{ExrChannel, ExrCompression, ExrPixelType, write_exr};
+use crate::film::Film;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Image file formats the renderer can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// ASCII P3 PPM.
+    PpmAscii,
+    /// Binary P6 PPM.
+    Ppm,
+    /// Binary P5 PGM of the image's luminance.
+    Pgm,
+    Png8,
+    Png16,
+    /// Linear, unclamped OpenEXR.
+    Exr { pixel_type: ExrPixelType, compression: ExrCompression },
+    /// Linear, unclamped Radiance RGBE.
+    Hdr,
+    /// Linear, unclamped portable float map.
+    Pfm
+}
+
+impl ImageFormat {
+    /// Whether the format stores unclamped linear values.
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, ImageFormat::Exr { .. } | ImageFormat::Hdr | ImageFormat::Pfm)
+    }
+
+    /// Picks a format from the extension of `path`: `.ppm`, `.pgm`, `.png`, `.exr` (half
+    /// floats, ZIP compressed), `.hdr` or `.pfm`.
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "pgm" => Some(ImageFormat::Pgm),
+            "png" => Some(ImageFormat::Png8),
+            "exr" => Some(ImageFormat::Exr { pixel_type: ExrPixelType::Half, compression: ExrCompression::Zip }),
+            "hdr" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None
+        }
+    }
+}
+
+/// Writes `film` as an ASCII P3 PPM.
+pub fn write_ppm(stream: &mut dyn Write, film: &Film) -> io::Result<()> {
+    write!(stream, "P3\n{} {}\n255\n", film.width, film.height)?;
+    for pixel_color in film.pixels() {
+        write_color(stream, pixel_color)?;
+    }
+    Ok(())
+}
+
+/// Writes `film` as a binary P6 PPM.
+pub fn write_ppm_binary(stream: &mut dyn Write, film: &Film) -> io::Result<()> {
+    ppm_binary(stream, film, to_bytes)
+}
+
+fn ppm_binary(stream: &mut dyn Write, film: &Film, quantize: fn(Color) -> [u8; 3]) -> io::Result<()> {
+    write!(stream, "P6\n{} {}\n255\n", film.width, film.height)?;
+    let data: Vec<u8> = film.pixels().flat_map(quantize).collect();
+    stream.write_all(&data)
+}
+
+/// Writes the luminance of `film` as a binary P5 PGM.
+pub fn write_pgm_binary(stream: &mut dyn Write, film: &Film) -> io::Result<()> {
+    pgm_binary(stream, film, to_gray_byte)
+}
+
+fn pgm_binary(stream: &mut dyn Write, film: &Film, quantize: fn(Color) -> u8) -> io::Result<()> {
+    write!(stream, "P5\n{} {}\n255\n", film.width, film.height)?;
+    let data: Vec<u8> = film.pixels().map(quantize).collect();
+    stream.write_all(&data)
+}
+
+/// Writes `film` as an 8 or 16 bit per channel RGB PNG.
+pub fn write_png(stream: &mut dyn Write, film: &Film, sixteen_bit: bool) -> io::Result<()> {
+    png(stream, film, sixteen_bit, Transfer::Srgb)
+}
+
+/// How linear values become integer samples.
+#[derive(Clone, Copy)]
+enum Transfer {
+    Srgb,
+    /// Stored as they are, for data that is not a color.
+    Linear
+}
+
+impl Transfer {
+    fn bytes(self, value: Color) -> [u8; 3] {
+        match self {
+            Transfer::Srgb => to_bytes(value),
+            Transfer::Linear => to_linear_bytes(value)
+        }
+    }
+
+    fn words(self, value: Color) -> [u16; 3] {
+        match self {
+            Transfer::Srgb => to_words(value),
+            Transfer::Linear => to_linear_words(value)
+        }
+    }
+}
+
+fn png(stream: &mut dyn Write, film: &Film, sixteen_bit: bool, transfer: Transfer) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(stream, film.width as u32, film.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+
+    let data: Vec<u8> = if sixteen_bit {
+        encoder.set_depth(png::BitDepth::Sixteen);
+        film.pixels().flat_map(|value| transfer.words(value)).flat_map(u16::to_be_bytes).collect()
+    } else {
+        encoder.set_depth(png::BitDepth::Eight);
+        film.pixels().flat_map(|value| transfer.bytes(value)).collect()
+    };
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Writes the linear colors of `film` as an RGB OpenEXR image labelled with the chromaticities
+/// of the film's color space. AOVs collected by the film are written alongside as `albedo`,
+/// `normal`, `depth`, `objectId`, `background` and `sky` layers, with object IDs always stored
+/// as full floats so that they stay exact.
+pub fn write_exr_film(stream: &mut dyn Write, film: &Film, pixel_type: ExrPixelType, compression: ExrCompression) -> io::Result<()> {
+    let mut channels = rgb_channels("", &film.to_colors(), ["R", "G", "B"]);
+    if let Some(aovs) = film.aovs() {
+        channels.extend(rgb_channels("albedo.", &aovs.albedo, ["R", "G", "B"]));
+        channels.extend(rgb_channels("normal.", &aovs.normal, ["X", "Y", "Z"]));
+        channels.push(ExrChannel::new("depth.Z", aovs.depth.iter().map(|&depth| depth as f32).collect()));
+        let ids = aovs.object_id.iter().map(|&id| id as f32).collect();
+        channels.push(ExrChannel::new("objectId", ids).stored_as(ExrPixelType::Float));
+        channels.extend(rgb_channels("background.", &aovs.background, ["R", "G", "B"]));
+        channels.extend(rgb_channels("sky.", &aovs.sky, ["R", "G", "B"]));
+    }
+    write_exr(stream, film.width, film.height, &channels, film.color_space().chromaticities(), pixel_type, compression)
+}
+
+fn rgb_channels(layer: &str, values: &[Vec3], names: [&str; 3]) -> Vec<ExrChannel> {
+    (0..3)
+        .map(|component| ExrChannel::new(
+            format!("{}{}", layer, names[component]),
+            values.iter().map(|value| value[component] as f32).collect()
+        ))
+        .collect()
+}
+
+/// Writes the linear colors of `film` as a run-length encoded Radiance RGBE image.
+pub fn write_hdr(stream: &mut dyn Write, film: &Film) -> io::Result<()> {
+    write!(stream, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", film.height, film.width)?;
+
+    let pixels: Vec<[u8; 4]> = film.pixels().map(to_rgbe).collect();
+    for row in pixels.chunks(film.width.max(1)) {
+        if !(8..0x8000).contains(&film.width) {
+            stream.write_all(row.as_flattened())?;
+            continue;
+        }
+
+        let mut data = vec![2, 2, (film.width >> 8) as u8, (film.width & 0xff) as u8];
+        for component in 0..4 {
+            let values: Vec<u8> = row.iter().map(|rgbe| rgbe[component]).collect();
+            rle_encode(&values, &mut data);
+        }
+        stream.write_all(&data)?;
+    }
+    Ok(())
+}
+
+/// Shared-exponent encoding of a linear color, with negative components clamped to zero and
+/// components too bright to encode, including infinities, clamped to the largest RGBE value.
+fn to_rgbe(pixel_color: Color) -> [u8; 4] {
+    // A mantissa of 255 with the exponent byte 255.
+    let max = 255.0 * 2f64.powi(119);
+    let Color { x, y, z } = pixel_color;
+    let (r, g, b) = (x.max(0.0).min(max), y.max(0.0).min(max), z.max(0.0).min(max));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let exponent = v.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f64.powi(exponent);
+    [(r * scale) as u8, (g * scale) as u8, (b * scale) as u8, (exponent + 128) as u8]
+}
+
+/// Appends `values` to `data` as Radiance scanline runs: a count above 128 repeats the next
+/// byte `count - 128` times, otherwise `count` literal bytes follow.
+fn rle_encode(values: &[u8], data: &mut Vec<u8>) {
+    const MIN_RUN: usize = 4;
+    let mut start = 0;
+    while start < values.len() {
+        let mut run_start = start;
+        let mut run_length = 0;
+        while run_start < values.len() {
+            run_length = values[run_start..].iter().take(127).take_while(|&&v| v == values[run_start]).count();
+            if run_length >= MIN_RUN {
+                break;
+            }
+            run_start += run_length;
+        }
+        if run_length < MIN_RUN {
+            run_start = values.len();
+        }
+
+        for literals in values[start..run_start].chunks(128) {
+            data.push(literals.len() as u8);
+            data.extend_from_slice(literals);
+        }
+        if run_start < values.len() {
+            data.push(128 + run_length as u8);
+            data.push(values[run_start]);
+        }
+        start = run_start + run_length;
+    }
+}
+
+/// Writes the linear colors of `film` as a little-endian color PFM, bottom row first.
+pub fn write_pfm(stream: &mut dyn Write, film: &Film) -> io::Result<()> {
+    write!(stream, "PF\n{} {}\n-1.0\n", film.width, film.height)?;
+    let pixels = film.to_colors();
+    for row in pixels.chunks(film.width.max(1)).rev() {
+        let data: Vec<u8> = row
+            .iter()
+            .flat_map(|pixel_color| [pixel_color.x, pixel_color.y, pixel_color.z])
+            .flat_map(|component| (component as f32).to_le_bytes())
+            .collect();
+        stream.write_all(&data)?;
+    }
+    Ok(())
+}
+
+/// Writes `film` to `stream` in `format`.
+pub fn write_image(stream: &mut dyn Write, film: &Film, format: ImageFormat) -> io::Result<()> {
+    match format {
+        ImageFormat::PpmAscii => write_ppm(stream, film),
+        ImageFormat::Ppm => write_ppm_binary(stream, film),
+        ImageFormat::Pgm => write_pgm_binary(stream, film),
+        ImageFormat::Png8 => write_png(stream, film, false),
+        ImageFormat::Png16 => write_png(stream, film, true),
+        ImageFormat::Exr { pixel_type, compression } => write_exr_film(stream, film, pixel_type, compression),
+        ImageFormat::Hdr => write_hdr(stream, film),
+        ImageFormat::Pfm => write_pfm(stream, film)
+    }
+}
+
+/// Writes `film` to `stream` in `format` like [`write_image`], except that integer formats store
+/// values linearly, without the sRGB transfer function, so that data such as normals and depths
+/// can be decoded.
+pub fn write_data_image(stream: &mut dyn Write, film: &Film, format: ImageFormat) -> io::Result<()> {
+    match format {
+        ImageFormat::PpmAscii => {
+            write!(stream, "P3\n{} {}\n255\n", film.width, film.height)?;
+            for value in film.pixels() {
+                let [r, g, b] = to_linear_bytes(value);
+                writeln!(stream, "{} {} {}", r, g, b)?;
+            }
+            Ok(())
+        }
+        ImageFormat::Ppm => ppm_binary(stream, film, to_linear_bytes),
+        ImageFormat::Pgm => pgm_binary(stream, film, |value| {
+            let gray = luminance(value);
+            to_linear_bytes(Color::new(gray, gray, gray))[0]
+        }),
+        ImageFormat::Png8 => png(stream, film, false, Transfer::Linear),
+        ImageFormat::Png16 => png(stream, film, true, Transfer::Linear),
+        ImageFormat::Exr { .. } | ImageFormat::Hdr | ImageFormat::Pfm => write_image(stream, film, format)
+    }
+}
+
+/// The format given by the extension of `path`, or an `InvalidInput` error naming the path.
+pub fn format_for(path: &Path) -> io::Result<ImageFormat> {
+    ImageFormat::from_path(path).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Unsupported image extension in {}", path.display())
+    ))
+}
+
+/// Writes `film` to a new file at `path` in the format given by its extension, replacing
+/// any existing file.
+pub fn save(path: &Path, film: &Film) -> io::Result<()> {
+    save_as(path, film, format_for(path)?)
+}
+
+/// Writes `film` to a new file at `path` in `format`, replacing any existing file.
+pub fn save_as(path: &Path, film: &Film, format: ImageFormat) -> io::Result<()> {
+    save_with(path, film, format, write_image)
+}
+
+/// A function writing a film to a stream in a format, such as [`write_image`].
+type ImageWriter = fn(&mut dyn Write, &Film, ImageFormat) -> io::Result<()>;
+
+fn save_with(path: &Path, film: &Film, format: ImageFormat, write: ImageWriter) -> io::Result<()> {
+    let mut stream = BufWriter::new(File::create(path)?);
+    write(&mut stream, film, format)?;
+    stream.flush()
+}
+
+/// Writes the AOVs collected by `film` next to the image at `path`, as `<stem>.albedo.<ext>`
+/// and so on in `format`, returning the paths written. The albedo and the `background` and
+/// `sky` light groups are colors; normals, depths and object IDs are data, written with
+/// [`write_data_image`]. OpenEXR images already carry the AOVs as layers, so nothing is written
+/// for them.
+pub fn save_aov_siblings(path: &Path, film: &Film, format: ImageFormat) -> io::Result<Vec<PathBuf>> {
+    let Some(aovs) = film.aovs() else { return Ok(vec![]) };
+    if let ImageFormat::Exr { .. } = format {
+        return Ok(vec![]);
+    }
+
+    let color: ImageWriter = write_image;
+    let data: ImageWriter = write_data_image;
+    let layers = [
+        ("albedo", aovs.albedo_film(), color),
+        ("normal", aovs.normal_film(), data),
+        ("depth", aovs.depth_film(!format.is_hdr()), data),
+        ("object_id", aovs.object_id_film(), data),
+        ("background", aovs.background_film(), color),
+        ("sky", aovs.sky_film(), color)
+    ];
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    layers
+        .iter()
+        .map(|(name, layer, write)| {
+            let sibling = path.with_file_name(format!("{}.{}.{}", stem, name, extension));
+            save_with(&sibling, layer, format, *write)?;
+            Ok(sibling)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    fn film() -> Film {
+        Film::from_colors(2, 1, vec![Color::ones(), Color::new(0.0, 0.25, 1.0)])
+    }
+
+    #[test]
+    fn from_path() {
+        assert_eq!(ImageFormat::from_path(Path::new("render.PNG")), Some(ImageFormat::Png8));
+        assert_eq!(ImageFormat::from_path(Path::new("out/render.ppm")), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::from_path(Path::new("render.pgm")), Some(ImageFormat::Pgm));
+        assert_eq!(ImageFormat::from_path(Path::new("render.jpg")), None);
+        assert_eq!(ImageFormat::from_path(Path::new("render")), None);
+    }
+
+    #[test]
+    fn hdr_formats_from_path() {
+        assert_eq!(
+            ImageFormat::from_path(Path::new("render.exr")),
+            Some(ImageFormat::Exr { pixel_type: ExrPixelType::Half, compression: ExrCompression::Zip })
+        );
+        assert_eq!(ImageFormat::from_path(Path::new("render.hdr")), Some(ImageFormat::Hdr));
+        assert_eq!(ImageFormat::from_path(Path::new("render.pfm")), Some(ImageFormat::Pfm));
+    }
+
+    #[test]
+    fn rgbe() {
+        assert_eq!(to_rgbe(Color::new(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(Color::new(-1.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Color::new(300.0, 0.0, 0.0))[3], 137);
+        assert_eq!(to_rgbe(Color::new(f64::INFINITY, 1.0, 0.0)), [255, 0, 0, 255]);
+        assert_eq!(to_rgbe(Color::new(1e40, 1e40, 1e40)), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn rle() {
+        let mut data = Vec::new();
+        rle_encode(&[1, 2, 3, 7, 7, 7, 7, 7, 4], &mut data);
+        assert_eq!(data, vec![3, 1, 2, 3, 133, 7, 1, 4]);
+
+        let mut data = Vec::new();
+        rle_encode(&[9; 200], &mut data);
+        assert_eq!(data, vec![255, 9, 128 + 73, 9]);
+    }
+
+    #[test]
+    fn pfm() {
+        let mut out = Vec::new();
+        let film = Film::from_colors(1, 2, vec![Color::new(2.0, 0.0, 0.0), Color::new(0.0, 0.0, 8.0)]);
+        write_pfm(&mut out, &film).unwrap();
+        assert_eq!(&out[..12], b"PF\n1 2\n-1.0\n");
+        assert_eq!(&out[20..24], &8f32.to_le_bytes());
+        assert_eq!(&out[24..28], &2f32.to_le_bytes());
+    }
+
+    #[test]
+    fn ppm_binary() {
+        let mut out = Vec::new();
+        write_ppm_binary(&mut out, &film()).unwrap();
+        assert_eq!(out, b"P6\n2 1\n255\n\xff\xff\xff\x00\x89\xff");
+    }
+
+    #[test]
+    fn pgm_binary() {
+        let mut out = Vec::new();
+        write_pgm_binary(&mut out, &film()).unwrap();
+        assert_eq!(&out[..11], b"P5\n2 1\n255\n");
+        assert_eq!(out.len(), 13);
+    }
+
+    #[test]
+    fn data_is_written_linearly() {
+        let normals = Film::from_colors(1, 1, vec![Color::new(0.5, 0.0, 1.0)]);
+        let mut out = Vec::new();
+        write_data_image(&mut out, &normals, ImageFormat::Ppm).unwrap();
+        assert_eq!(out, b"P6\n1 1\n255\n\x80\x00\xff");
+
+        let mut out = Vec::new();
+        write_data_image(&mut out, &normals, ImageFormat::PpmAscii).unwrap();
+        assert_eq!(out, b"P3\n1 1\n255\n128 0 255\n");
+    }
+
+    #[test]
+    fn png_round_trip() {
+        for sixteen_bit in [false, true] {
+            let mut out = Vec::new();
+            write_png(&mut out, &film(), sixteen_bit).unwrap();
+
+            let decoder = png::Decoder::new(out.as_slice());
+            let mut reader = decoder.read_info().unwrap();
+            let mut data = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut data).unwrap();
+            assert_eq!((info.width, info.height), (2, 1));
+            if sixteen_bit {
+                assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+                assert_eq!(&data[8..10], &35199u16.to_be_bytes());
+            } else {
+                assert_eq!(&data[..6], &[255, 255, 255, 0, 137, 255]);
+            }
+        }
+    }
+}
@@ -0,0 +1,833 @@
+use crate::camera::CameraBuilder;
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::geometry::{Scene, Sphere};
+use crate::mat3::Mat3;
+use crate::material::{Dielectric, Lambertian, Material, Metal};
+use crate::mesh::MeshData;
+use crate::ply;
+use crate::scene_file::SceneFileError;
+use crate::transform::{Instance, Transform};
+use crate::vec3::{Point3, Vec3};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Something in a pbrt file that was skipped or only approximated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Loads the pbrt-v3 or pbrt-v4 scene at `path`, finding the files it names next to it. See
+/// [`parse`] for what is understood.
+pub fn load(path: &Path) -> Result<(Scene, CameraBuilder, Vec<Warning>)> {
+    import(&fs::read_to_string(path)?, path.parent().unwrap_or(Path::new("")))
+}
+
+/// Builds the scene and camera described by pbrt `text`, with a warning for everything that
+/// had to be skipped or approximated. Understood are:
+///
+/// - `LookAt`, `Translate`, `Scale`, `Rotate`, `Transform`, `ConcatTransform`, `Identity`,
+///   `CoordinateSystem`, `CoordSysTransform` and `ReverseOrientation`, saved and restored by
+///   `AttributeBegin`/`AttributeEnd` and `TransformBegin`/`TransformEnd`.
+/// - A `perspective` `Camera` with `fov`, `lensradius` and `focaldistance`, the `Film`
+///   resolution, the `Sampler`'s `pixelsamples` and the `Integrator`'s `maxdepth`.
+/// - `diffuse`, `conductor` and `dielectric` materials and their pbrt-v3 forms `matte`,
+///   `metal`, `mirror` and `glass`, set by `Material` or named by `MakeNamedMaterial`. Other
+///   materials become diffuse.
+/// - `sphere`, `trianglemesh` and `plymesh` shapes. PLY files are found relative to the
+///   working directory, or next to the scene when it is read by [`load`].
+///
+/// Lights, textures and spectral colors are among what is skipped; the crate lights every
+/// scene with its sky. pbrt's camera space is left-handed, so the scene is mirrored where
+/// needed for images to come out the same way round as pbrt's.
+pub fn parse(text: &str) -> Result<(Scene, CameraBuilder, Vec<Warning>)> {
+    import(text, Path::new(""))
+}
+
+/// Like [`parse`], finding files named by the scene in `directory`.
+fn import(text: &str, directory: &Path) -> Result<(Scene, CameraBuilder, Vec<Warning>)> {
+    let mut tokens = Tokenizer { text, pos: 0, line: 1 };
+    let mut importer = Importer::new(directory);
+
+    let mut next = tokens.next()?;
+    while let Some((token, line)) = next.take() {
+        let Token::Word(directive) = token else {
+            return Err(file_error(line, "", "expected a directive"));
+        };
+        let mut args = vec![];
+        next = tokens.next()?;
+        while let Some((token, arg_line)) = next.take() {
+            if matches!(token, Token::Word(word) if word != "true" && word != "false") {
+                next = Some((token, arg_line));
+                break;
+            }
+            args.push(tokens.values(token, arg_line)?);
+            next = tokens.next()?;
+        }
+        importer.directive(directive, line, args);
+    }
+    importer.finish()
+}
+
+fn file_error(line: usize, path: &str, message: impl ToString) -> Error {
+    Error::SceneFile(vec![SceneFileError { line, path: path.to_string(), message: message.to_string() }])
+}
+
+enum Token<'a> {
+    Word(&'a str),
+    Str(&'a str),
+    Number(f64),
+    Open,
+    Close
+}
+
+/// Splits pbrt text into tokens as they are needed, so large meshes are never held twice.
+struct Tokenizer<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize
+}
+
+impl<'a> Tokenizer<'a> {
+    /// The next token and the line it starts on.
+    fn next(&mut self) -> Result<Option<(Token<'a>, usize)>> {
+        let bytes = self.text.as_bytes();
+        while let Some(&byte) = bytes.get(self.pos) {
+            match byte {
+                b'\n' => self.line += 1,
+                b'#' => {
+                    while bytes.get(self.pos + 1).is_some_and(|&byte| byte != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => {}
+                _ => break
+            }
+            self.pos += 1;
+        }
+
+        let start = self.pos;
+        let token = match bytes.get(start) {
+            None => return Ok(None),
+            Some(b'[') => Token::Open,
+            Some(b']') => Token::Close,
+            Some(b'"') => match self.text[start + 1..].find(['"', '\n']).map(|offset| start + 1 + offset) {
+                Some(end) if bytes[end] == b'"' => {
+                    self.pos = end;
+                    Token::Str(&self.text[start + 1..end])
+                }
+                _ => return Err(file_error(self.line, "", "unterminated string"))
+            },
+            Some(&first) => {
+                while bytes.get(self.pos + 1).is_some_and(|&byte| !byte.is_ascii_whitespace() && !b"\"[]#".contains(&byte)) {
+                    self.pos += 1;
+                }
+                let word = &self.text[start..=self.pos];
+                if first.is_ascii_digit() || b"+-.".contains(&first) {
+                    Token::Number(word.parse().map_err(|_| file_error(self.line, "", format!("invalid number `{}`", word)))?)
+                } else {
+                    Token::Word(word)
+                }
+            }
+        };
+        self.pos += 1;
+        Ok(Some((token, self.line)))
+    }
+
+    /// The argument starting with `token`: a single value, or a bracketed list of them.
+    fn values(&mut self, token: Token<'a>, line: usize) -> Result<Values<'a>> {
+        let mut values = match token {
+            Token::Open => Values::Numbers(vec![]),
+            token => return Values::single(token).ok_or_else(|| file_error(line, "", "unexpected `]`"))
+        };
+        loop {
+            let Some((token, line)) = self.next()? else {
+                return Err(file_error(line, "", "unterminated list"));
+            };
+            match (&mut values, token) {
+                (_, Token::Close) => return Ok(values),
+                (Values::Numbers(numbers), Token::Number(number)) => numbers.push(number),
+                (Values::Strings(strings), Token::Str(string)) => strings.push(string),
+                (Values::Bools(bools), Token::Word(word @ ("true" | "false"))) => bools.push(word == "true"),
+                (Values::Numbers(numbers), token) if numbers.is_empty() => match Values::single(token) {
+                    Some(Values::Numbers(_)) | None => return Err(file_error(line, "", "unexpected token in list")),
+                    Some(single) => values = single
+                },
+                _ => return Err(file_error(line, "", "lists must not mix numbers, strings and bools"))
+            }
+        }
+    }
+}
+
+/// The values of an argument or parameter, which are always of one kind.
+enum Values<'a> {
+    Numbers(Vec<f64>),
+    Strings(Vec<&'a str>),
+    Bools(Vec<bool>)
+}
+
+impl<'a> Values<'a> {
+    fn single(token: Token<'a>) -> Option<Values<'a>> {
+        match token {
+            Token::Number(number) => Some(Values::Numbers(vec![number])),
+            Token::Str(string) => Some(Values::Strings(vec![string])),
+            Token::Word(word @ ("true" | "false")) => Some(Values::Bools(vec![word == "true"])),
+            _ => None
+        }
+    }
+}
+
+/// A directive's parameters, such as `"float radius" 2`, which remember which of them were
+/// looked up so that the rest can be reported.
+struct Params<'a> {
+    list: Vec<Param<'a>>
+}
+
+struct Param<'a> {
+    kind: &'a str,
+    name: &'a str,
+    values: Values<'a>,
+    used: Cell<bool>
+}
+
+impl<'a> Params<'a> {
+    fn parse(args: impl Iterator<Item = Values<'a>>) -> std::result::Result<Params<'a>, String> {
+        let mut list = vec![];
+        let mut args = args.peekable();
+        while let Some(declaration) = args.next() {
+            let declaration = match declaration {
+                Values::Strings(strings) if strings.len() == 1 => strings[0],
+                _ => return Err("expected a parameter like \"float radius\"".to_string())
+            };
+            let mut words = declaration.split_whitespace();
+            let (Some(kind), Some(name), None) = (words.next(), words.next(), words.next()) else {
+                return Err(format!("expected a type and a name in \"{}\"", declaration));
+            };
+            let values = args.next().ok_or_else(|| format!("missing the value of \"{}\"", declaration))?;
+            list.push(Param { kind, name, values, used: Cell::new(false) });
+        }
+        Ok(Params { list })
+    }
+
+    fn find(&self, kinds: &[&str], name: &str) -> Option<&Values<'a>> {
+        let param = self.list.iter().find(|param| param.name == name && kinds.contains(&param.kind))?;
+        param.used.set(true);
+        Some(&param.values)
+    }
+
+    /// Marks a parameter as understood without looking at it.
+    fn accept(&self, name: &str) {
+        self.list.iter().filter(|param| param.name == name).for_each(|param| param.used.set(true));
+    }
+
+    fn numbers(&self, kinds: &[&str], name: &str) -> std::result::Result<Option<&[f64]>, String> {
+        match self.find(kinds, name) {
+            None => Ok(None),
+            Some(Values::Numbers(numbers)) => Ok(Some(numbers)),
+            Some(_) => Err(format!("\"{}\" must be numbers", name))
+        }
+    }
+
+    fn float(&self, name: &str) -> std::result::Result<Option<f64>, String> {
+        match self.numbers(&["float"], name)? {
+            None => Ok(None),
+            Some(&[value]) => Ok(Some(value)),
+            Some(_) => Err(format!("\"{}\" must be a single number", name))
+        }
+    }
+
+    fn integer(&self, name: &str) -> std::result::Result<Option<u32>, String> {
+        match self.numbers(&["integer"], name)? {
+            None => Ok(None),
+            Some(&[value]) if value >= 0.0 && value.fract() == 0.0 => Ok(Some(value as u32)),
+            Some(_) => Err(format!("\"{}\" must be a single non-negative integer", name))
+        }
+    }
+
+    fn string(&self, name: &str) -> std::result::Result<Option<&'a str>, String> {
+        match self.find(&["string"], name) {
+            None => Ok(None),
+            Some(Values::Strings(strings)) if strings.len() == 1 => Ok(Some(strings[0])),
+            Some(_) => Err(format!("\"{}\" must be a single string", name))
+        }
+    }
+
+    /// An RGB color; other ways of giving a color are left unused, and so reported.
+    fn color(&self, name: &str) -> std::result::Result<Option<Color>, String> {
+        match self.numbers(&["rgb", "color"], name)? {
+            None => Ok(None),
+            Some(&[r, g, b]) => Ok(Some(Color::new(r, g, b))),
+            Some(_) => Err(format!("\"{}\" must have three components", name))
+        }
+    }
+
+    /// The name of a built-in spectrum, such as `metal-Cu-eta`.
+    fn named_spectrum(&self, name: &str) -> Option<&'a str> {
+        match self.list.iter().find(|param| param.name == name && param.kind == "spectrum")?.values {
+            Values::Strings(ref strings) if strings.len() == 1 => {
+                self.accept(name);
+                Some(strings[0])
+            }
+            _ => None
+        }
+    }
+
+    fn unused(&self) -> impl Iterator<Item = &Param<'a>> {
+        self.list.iter().filter(|param| !param.used.get())
+    }
+}
+
+/// Transform state that `AttributeBegin` saves and `AttributeEnd` restores.
+#[derive(Clone)]
+struct State {
+    /// Maps the current object space to pbrt world space.
+    ctm: Transform,
+    reverse_orientation: bool,
+    material: Arc<dyn Material>
+}
+
+/// What the `Camera` directive sets up.
+struct CameraSettings {
+    camera_to_world: Transform,
+    fov: f64,
+    lens_radius: f64,
+    focal_distance: f64
+}
+
+/// The reflection of x, which turns pbrt's left-handed camera space into a right-handed one.
+const MIRROR: Transform = Transform::scale(Vec3::new(-1.0, 1.0, 1.0));
+
+/// Carries out directives one at a time, collecting errors and warnings as it goes.
+struct Importer {
+    world: Scene,
+    state: State,
+    /// Saved states, and whether they were saved by `TransformBegin`, which restores only the
+    /// transform.
+    stack: Vec<(State, bool)>,
+    named_materials: HashMap<String, Arc<dyn Material>>,
+    coordinate_systems: HashMap<String, Transform>,
+    camera: CameraSettings,
+    /// Maps pbrt world space to the crate's, mirroring it unless pbrt's camera already does.
+    to_world: Transform,
+    resolution: (usize, usize),
+    samples_per_pixel: Option<u32>,
+    max_depth: Option<u32>,
+    /// Inside `ObjectBegin`, whose shapes are skipped.
+    in_object: bool,
+    /// Where the files the scene names are found.
+    directory: PathBuf,
+    errors: Vec<SceneFileError>,
+    warnings: Vec<Warning>
+}
+
+impl Importer {
+    fn new(directory: &Path) -> Importer {
+        Importer {
+            world: Scene::new(),
+            state: State { ctm: Transform::identity(), reverse_orientation: false, material: Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))) },
+            stack: vec![],
+            named_materials: HashMap::new(),
+            coordinate_systems: HashMap::new(),
+            camera: CameraSettings { camera_to_world: Transform::identity(), fov: 90.0, lens_radius: 0.0, focal_distance: 1e6 },
+            to_world: MIRROR,
+            resolution: (1280, 720),
+            samples_per_pixel: None,
+            max_depth: None,
+            in_object: false,
+            directory: directory.to_path_buf(),
+            errors: vec![],
+            warnings: vec![]
+        }
+    }
+
+    fn warn(&mut self, line: usize, message: impl ToString) {
+        self.warnings.push(Warning { line, message: message.to_string() });
+    }
+
+    fn directive(&mut self, name: &str, line: usize, args: Vec<Values>) {
+        if let Err(message) = self.apply(name, line, args) {
+            self.errors.push(SceneFileError { line, path: name.to_string(), message });
+        }
+    }
+
+    fn apply(&mut self, name: &str, line: usize, args: Vec<Values>) -> std::result::Result<(), String> {
+        match name {
+            "Identity" => self.state.ctm = Transform::identity(),
+            "Translate" => {
+                let [x, y, z] = numbers(args)?;
+                self.concat(Transform::translate(Vec3::new(x, y, z)));
+            }
+            "Scale" => {
+                let [x, y, z] = numbers(args)?;
+                self.concat(Transform::scale(Vec3::new(x, y, z)));
+            }
+            "Rotate" => {
+                let [degrees, x, y, z] = numbers(args)?;
+                if Vec3::new(x, y, z).near_zero() {
+                    return Err("the rotation axis must not be zero".to_string());
+                }
+                self.concat(Transform::rotate(Vec3::new(x, y, z), degrees));
+            }
+            "LookAt" => {
+                let [ex, ey, ez, lx, ly, lz, ux, uy, uz] = numbers(args)?;
+                let (eye, look, up) = (Point3::new(ex, ey, ez), Point3::new(lx, ly, lz), Vec3::new(ux, uy, uz));
+                let dir = (look - eye).unit();
+                let right = Vec3::cross(up.unit(), dir);
+                if (look - eye).near_zero() || right.near_zero() {
+                    return Err("the eye, target and up vector must not be degenerate".to_string());
+                }
+                let right = right.unit();
+                let new_up = Vec3::cross(dir, right);
+                let linear = Mat3::new([[right.x, new_up.x, dir.x], [right.y, new_up.y, dir.y], [right.z, new_up.z, dir.z]]);
+                let camera_to_world = Transform { linear, translation: eye };
+                self.concat(camera_to_world.inverse().ok_or("the view must not be degenerate")?);
+            }
+            "Transform" | "ConcatTransform" => {
+                let matrix: [f64; 16] = numbers(args)?;
+                // pbrt lists matrices column by column.
+                if matrix[3] != 0.0 || matrix[7] != 0.0 || matrix[11] != 0.0 || matrix[15] != 1.0 {
+                    self.warn(line, format!("{} has a projective part, which is ignored", name));
+                }
+                let linear = Mat3::new([[matrix[0], matrix[4], matrix[8]], [matrix[1], matrix[5], matrix[9]], [matrix[2], matrix[6], matrix[10]]]);
+                let transform = Transform { linear, translation: Vec3::new(matrix[12], matrix[13], matrix[14]) };
+                if name == "Transform" {
+                    self.state.ctm = transform;
+                } else {
+                    self.concat(transform);
+                }
+            }
+            "CoordinateSystem" => {
+                let [name] = strings(args)?;
+                self.coordinate_systems.insert(name.to_string(), self.state.ctm);
+            }
+            "CoordSysTransform" => {
+                let [name] = strings(args)?;
+                match self.coordinate_systems.get(name) {
+                    Some(&ctm) => self.state.ctm = ctm,
+                    None => self.warn(line, format!("unknown coordinate system \"{}\" ignored", name))
+                }
+            }
+            "ReverseOrientation" => self.state.reverse_orientation = !self.state.reverse_orientation,
+            "AttributeBegin" | "TransformBegin" => self.stack.push((self.state.clone(), name == "TransformBegin")),
+            "AttributeEnd" | "TransformEnd" => {
+                let (state, transform_only) = self.stack.pop().ok_or(format!("{} without a matching begin", name))?;
+                if transform_only {
+                    self.state.ctm = state.ctm;
+                } else {
+                    self.state = state;
+                }
+            }
+            "WorldBegin" => {
+                self.state.ctm = Transform::identity();
+                self.coordinate_systems.insert("world".to_string(), Transform::identity());
+            }
+            "WorldEnd" => {}
+            "Camera" => self.camera(line, args)?,
+            "Film" => {
+                let ([kind], params) = positional::<1>(args)?;
+                let width = params.integer("xresolution")?.unwrap_or(self.resolution.0 as u32);
+                let height = params.integer("yresolution")?.unwrap_or(self.resolution.1 as u32);
+                if width == 0 || height == 0 {
+                    return Err("the resolution must not be zero".to_string());
+                }
+                self.resolution = (width as usize, height as usize);
+                // The output file is chosen when rendering.
+                params.accept("filename");
+                self.warn_unused(line, &format!("Film \"{}\"", kind), &params);
+            }
+            "Sampler" => {
+                let ([kind], params) = positional::<1>(args)?;
+                self.samples_per_pixel = params.integer("pixelsamples")?.or(self.samples_per_pixel);
+                self.warn_unused(line, &format!("Sampler \"{}\"", kind), &params);
+            }
+            "Integrator" => {
+                let ([kind], params) = positional::<1>(args)?;
+                self.max_depth = params.integer("maxdepth")?.or(self.max_depth);
+                self.warn_unused(line, &format!("Integrator \"{}\"", kind), &params);
+            }
+            "Material" => {
+                let ([kind], params) = positional::<1>(args)?;
+                self.state.material = self.material(line, kind, &params)?;
+                self.warn_unused(line, &format!("Material \"{}\"", kind), &params);
+            }
+            "MakeNamedMaterial" => {
+                let ([material_name], params) = positional::<1>(args)?;
+                let kind = params.string("type")?.ok_or("missing \"string type\"")?;
+                let material = self.material(line, kind, &params)?;
+                self.named_materials.insert(material_name.to_string(), material);
+                self.warn_unused(line, &format!("MakeNamedMaterial \"{}\"", material_name), &params);
+            }
+            "NamedMaterial" => {
+                let [material_name] = strings(args)?;
+                let material = self.named_materials.get(material_name).ok_or(format!("unknown material \"{}\"", material_name))?;
+                self.state.material = material.clone();
+            }
+            "Shape" => {
+                let ([kind], params) = positional::<1>(args)?;
+                if !self.in_object {
+                    self.shape(line, kind, &params)?;
+                }
+                self.warn_unused(line, &format!("Shape \"{}\"", kind), &params);
+            }
+            "LightSource" | "AreaLightSource" => self.warn(line, format!("{} skipped: lights are not supported, so the scene is lit by the sky", name)),
+            "ObjectBegin" => {
+                self.warn(line, "ObjectBegin skipped along with its shapes: instancing is not supported");
+                self.stack.push((self.state.clone(), false));
+                self.in_object = true;
+            }
+            "ObjectEnd" => {
+                let (state, _) = self.stack.pop().ok_or("ObjectEnd without a matching begin")?;
+                self.state = state;
+                self.in_object = false;
+            }
+            _ => self.warn(line, format!("unsupported directive {} skipped", name))
+        }
+        Ok(())
+    }
+
+    /// Applies `transform` to objects before the current transform.
+    fn concat(&mut self, transform: Transform) {
+        self.state.ctm = transform.then(&self.state.ctm);
+    }
+
+    fn camera(&mut self, line: usize, args: Vec<Values>) -> std::result::Result<(), String> {
+        let ([kind], params) = positional::<1>(args)?;
+        if kind != "perspective" {
+            self.warn(line, format!("unsupported camera \"{}\" replaced by a perspective one", kind));
+        }
+        let camera_to_world = self.state.ctm.inverse().ok_or("the camera transform must be invertible")?;
+        self.camera = CameraSettings {
+            camera_to_world,
+            fov: params.float("fov")?.unwrap_or(90.0),
+            lens_radius: params.float("lensradius")?.unwrap_or(0.0),
+            focal_distance: params.float("focaldistance")?.unwrap_or(1e6)
+        };
+        // A camera that already mirrors its view matches the crate's right-handed one.
+        self.to_world = if camera_to_world.linear.determinant() > 0.0 { MIRROR } else { Transform::identity() };
+        self.coordinate_systems.insert("camera".to_string(), camera_to_world);
+        self.warn_unused(line, &format!("Camera \"{}\"", kind), &params);
+        Ok(())
+    }
+
+    fn material(&mut self, line: usize, kind: &str, params: &Params) -> std::result::Result<Arc<dyn Material>, String> {
+        let reflectance = |default| -> std::result::Result<Color, String> {
+            Ok(params.color("reflectance")?.or(params.color("Kd")?).unwrap_or(default))
+        };
+        let material: Result<Arc<dyn Material>> = match kind {
+            "diffuse" | "matte" => Ok(Arc::new(Lambertian::new(reflectance(Color::new(0.5, 0.5, 0.5))?))),
+            "conductor" | "metal" | "mirror" => {
+                let albedo = match params.color("reflectance")?.or(params.color("Kr")?) {
+                    Some(albedo) => albedo,
+                    None if kind == "mirror" => Color::new(0.9, 0.9, 0.9),
+                    None => {
+                        let spectrum = params.named_spectrum("eta").unwrap_or("metal-Cu-eta");
+                        params.accept("k");
+                        metal_color(spectrum).unwrap_or_else(|| {
+                            self.warn(line, format!("unknown metal \"{}\" replaced by copper", spectrum));
+                            metal_color("metal-Cu-eta").unwrap()
+                        })
+                    }
+                };
+                let roughness = match (params.float("roughness")?, params.float("uroughness")?, params.float("vroughness")?) {
+                    (_, Some(u), Some(v)) => (u + v) / 2.0,
+                    (Some(roughness), _, _) | (None, Some(roughness), None) | (None, None, Some(roughness)) => roughness,
+                    (None, None, None) => 0.0
+                };
+                params.accept("remaproughness");
+                Metal::try_new(albedo, roughness.clamp(0.0, 1.0)).map(|metal| Arc::new(metal) as _)
+            }
+            "dielectric" | "glass" | "thindielectric" => {
+                if kind == "thindielectric" {
+                    self.warn(line, "thindielectric approximated by dielectric");
+                }
+                let eta = match params.float("eta")?.or(params.float("index")?) {
+                    Some(eta) => eta,
+                    None => match params.named_spectrum("eta") {
+                        Some(spectrum) => glass_index(spectrum).unwrap_or_else(|| {
+                            self.warn(line, format!("unknown glass \"{}\" given an index of 1.5", spectrum));
+                            1.5
+                        }),
+                        None => 1.5
+                    }
+                };
+                Dielectric::try_new(eta).map(|glass| Arc::new(glass) as _)
+            }
+            _ => {
+                self.warn(line, format!("unsupported material \"{}\" approximated by diffuse", kind));
+                Ok(Arc::new(Lambertian::new(reflectance(Color::new(0.5, 0.5, 0.5))?)))
+            }
+        };
+        material.map_err(|err| err.to_string())
+    }
+
+    fn shape(&mut self, line: usize, kind: &str, params: &Params) -> std::result::Result<(), String> {
+        let to_world = self.state.ctm.then(&self.to_world);
+        match kind {
+            "sphere" => {
+                let radius = params.float("radius")?.unwrap_or(1.0);
+                let sphere = Sphere::try_new(Point3::zeroes(), radius, self.state.material.clone()).map_err(|err| err.to_string())?;
+                let instance = Instance::try_new(Box::new(sphere), to_world).map_err(|err| err.to_string())?;
+                self.world.add(Box::new(instance));
+            }
+            "trianglemesh" => {
+                let positions = params.numbers(&["point3", "point"], "P")?.ok_or("missing \"point3 P\"")?;
+                if positions.len() % 3 != 0 {
+                    return Err("\"P\" must hold whole points".to_string());
+                }
+                let indices = match params.numbers(&["integer"], "indices")? {
+                    Some(indices) => indices.to_vec(),
+                    None if positions.len() == 9 => vec![0.0, 1.0, 2.0],
+                    None => return Err("missing \"integer indices\"".to_string())
+                };
+                if indices.len() % 3 != 0 || indices.iter().any(|&index| index < 0.0 || index.fract() != 0.0) {
+                    return Err("\"indices\" must be whole triangles of non-negative integers".to_string());
+                }
+                let normals = params.numbers(&["normal", "normal3"], "N")?;
+                if normals.is_some_and(|normals| normals.len() != positions.len()) {
+                    return Err("\"N\" must have one normal per point".to_string());
+                }
+                // Texture coordinates have no use without textures.
+                params.accept("uv");
+                params.accept("st");
+
+                let mesh = MeshData {
+                    positions: positions.chunks_exact(3).map(|p| Point3::new(p[0], p[1], p[2])).collect(),
+                    normals: normals.map(|normals| normals.chunks_exact(3).map(|n| Vec3::new(n[0], n[1], n[2])).collect()),
+                    colors: None,
+                    triangles: indices.chunks_exact(3).map(|triangle| [triangle[0] as u32, triangle[1] as u32, triangle[2] as u32]).collect()
+                };
+                self.add_mesh(mesh, &to_world)?;
+            }
+            "plymesh" => {
+                let filename = params.string("filename")?.ok_or("missing \"string filename\"")?;
+                let mut mesh = ply::load(&self.directory.join(filename)).map_err(|err| format!("{}: {}", filename, err))?;
+                // pbrt leaves vertex colors to textures, which are not supported.
+                mesh.colors = None;
+                self.add_mesh(mesh, &to_world)?;
+            }
+            _ => {
+                params.list.iter().for_each(|param| param.used.set(true));
+                self.warn(line, format!("unsupported shape \"{}\" skipped", kind));
+            }
+        }
+        Ok(())
+    }
+
+    /// Places `mesh` by `to_world`, facing its triangles as pbrt would: by their winding,
+    /// unless the orientation is reversed or the transform changes handedness.
+    fn add_mesh(&mut self, mut mesh: MeshData, to_world: &Transform) -> std::result::Result<(), String> {
+        mesh.transform(to_world);
+        if self.state.reverse_orientation != (to_world.linear.determinant() < 0.0) {
+            mesh.flip();
+        }
+        let mesh = mesh.into_mesh(self.state.material.clone()).map_err(|err| err.to_string())?;
+        self.world.add(Box::new(mesh));
+        Ok(())
+    }
+
+    fn warn_unused(&mut self, line: usize, directive: &str, params: &Params) {
+        let unused: Vec<String> = params.unused().map(|param| format!("{} ignores \"{} {}\"", directive, param.kind, param.name)).collect();
+        for message in unused {
+            self.warn(line, message);
+        }
+    }
+
+    fn finish(self) -> Result<(Scene, CameraBuilder, Vec<Warning>)> {
+        if !self.errors.is_empty() {
+            return Err(Error::SceneFile(self.errors));
+        }
+
+        let (width, height) = self.resolution;
+        let mut aspect_ratio = width as f64 / height as f64;
+        while ((height as f64 * aspect_ratio) as usize) < width {
+            aspect_ratio = aspect_ratio.next_up();
+        }
+        // pbrt's field of view spans the shorter side of the image.
+        let half_fov = (self.camera.fov / 2.0).to_radians().tan();
+        let vfov = if width >= height { self.camera.fov } else { 2.0 * (half_fov * height as f64 / width as f64).atan().to_degrees() };
+        let defocus_angle = 2.0 * (self.camera.lens_radius / self.camera.focal_distance).atan().to_degrees();
+
+        let camera_to_world = self.camera.camera_to_world.then(&self.to_world);
+        let mut camera = CameraBuilder::new()
+            .aspect_ratio(aspect_ratio)
+            .image_height(height)
+            .vfov(vfov)
+            .lookfrom(camera_to_world.point(Point3::zeroes()))
+            .lookat(camera_to_world.point(Point3::new(0.0, 0.0, 1.0)))
+            .vup(camera_to_world.vector(Vec3::new(0.0, 1.0, 0.0)))
+            .defocus_angle(defocus_angle);
+        // Without a lens pbrt's default focal distance means nothing, and would put the
+        // viewport so far away that rays would skip nearby objects.
+        if self.camera.lens_radius > 0.0 { camera = camera.focus_dist(self.camera.focal_distance) };
+        if let Some(samples_per_pixel) = self.samples_per_pixel { camera = camera.samples_per_pixel(samples_per_pixel) };
+        if let Some(max_depth) = self.max_depth { camera = camera.max_depth(max_depth) };
+
+        Ok((self.world, camera, self.warnings))
+    }
+}
+
+/// Exactly `N` numbers, bare or in a list.
+fn numbers<const N: usize>(args: Vec<Values>) -> std::result::Result<[f64; N], String> {
+    let mut numbers = vec![];
+    for arg in args {
+        match arg {
+            Values::Numbers(values) => numbers.extend(values),
+            _ => return Err(format!("expected {} numbers", N))
+        }
+    }
+    let count = numbers.len();
+    numbers.try_into().map_err(|_| format!("expected {} numbers, found {}", N, count))
+}
+
+/// Exactly `N` strings.
+fn strings<'a, const N: usize>(args: Vec<Values<'a>>) -> std::result::Result<[&'a str; N], String> {
+    let (strings, params) = positional::<N>(args)?;
+    if !params.list.is_empty() {
+        return Err("takes no parameters".to_string());
+    }
+    Ok(strings)
+}
+
+/// `N` leading strings, such as a shape's kind, then the parameters.
+fn positional<'a, const N: usize>(args: Vec<Values<'a>>) -> std::result::Result<([&'a str; N], Params<'a>), String> {
+    let mut args = args.into_iter();
+    let mut strings = [""; N];
+    for string in strings.iter_mut() {
+        match args.next() {
+            Some(Values::Strings(values)) if values.len() == 1 => *string = values[0],
+            _ => return Err(format!("expected {} leading string{}", N, if N == 1 { "" } else { "s" }))
+        }
+    }
+    Ok((strings, Params::parse(args)?))
+}
+
+/// Approximate color of the metals whose spectra pbrt-v4 names.
+fn metal_color(spectrum: &str) -> Option<Color> {
+    match spectrum.strip_prefix("metal-")?.strip_suffix("-eta")? {
+        "Ag" => Some(Color::new(0.972, 0.960, 0.915)),
+        "Al" => Some(Color::new(0.913, 0.922, 0.924)),
+        "Au" => Some(Color::new(1.0, 0.766, 0.336)),
+        "Cu" => Some(Color::new(0.955, 0.638, 0.538)),
+        "CuZn" => Some(Color::new(0.910, 0.778, 0.423)),
+        _ => None
+    }
+}
+
+/// Refraction index, at 550nm, of the glasses whose spectra pbrt-v4 names.
+fn glass_index(spectrum: &str) -> Option<f64> {
+    match spectrum {
+        "glass-BK7" => Some(1.5185),
+        "glass-BAF10" => Some(1.6700),
+        "glass-FK51A" => Some(1.4875),
+        "glass-LASF9" => Some(1.8550),
+        "glass-F5" => Some(1.6054),
+        "glass-F10" => Some(1.6253),
+        "glass-F11" => Some(1.6245),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Hittable;
+    use crate::ray::Ray;
+
+    const SCENE: &str = r#"
+LookAt 0 1 -5  0 1 0  0 1 0   # eye, target, up
+Camera "perspective" "float fov" [ 30 ]
+Film "rgb" "integer xresolution" 200 "integer yresolution" 100 "string filename" "out.exr"
+Sampler "halton" "integer pixelsamples" 16
+
+WorldBegin
+LightSource "infinite" "rgb L" [1 1 1]
+MakeNamedMaterial "gold" "string type" "conductor" "spectrum eta" "metal-Au-eta" "float roughness" 0.1
+
+AttributeBegin
+  NamedMaterial "gold"
+  Translate 2 1 0
+  Shape "sphere" "float radius" 0.5 "float zmax" 0.2
+AttributeEnd
+
+Material "diffuse" "rgb reflectance" [0.2 0.4 0.6]
+Shape "trianglemesh" "point3 P" [-10 0 -10  10 0 -10  10 0 10  -10 0 10]
+    "integer indices" [0 2 1  0 3 2]
+"#;
+
+    #[test]
+    fn imports_camera_shapes_and_materials() {
+        let (world, camera, warnings) = parse(SCENE).unwrap();
+        assert_eq!(world.objects.len(), 2);
+        let messages: Vec<String> = warnings.iter().map(|warning| warning.to_string()).collect();
+        assert_eq!(messages, [
+            "line 8: LightSource skipped: lights are not supported, so the scene is lit by the sky",
+            "line 14: Shape \"sphere\" ignores \"float zmax\""
+        ]);
+
+        let camera = camera.build().unwrap();
+        assert_eq!((camera.image_width(), camera.image_height, camera.samples_per_pixel), (200, 100, 16));
+        assert_eq!(camera.vfov, 30.0);
+
+        // The ground faces up, towards the camera.
+        let rec = world.hit(&Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)), (0.001, f64::INFINITY)).unwrap();
+        assert!(rec.front_face && rec.t == 5.0);
+        assert_eq!(rec.mat.albedo(&rec), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn keeps_pbrt_handedness() {
+        let (world, camera, _) = parse(SCENE).unwrap();
+        let camera = camera.build().unwrap();
+
+        // pbrt shows the sphere at x = 2 on the right, seen from -z; so must the crate's camera.
+        let w = (camera.lookfrom - camera.lookat).unit();
+        let right = Vec3::cross(camera.vup, w);
+        let ray = Ray::new(camera.lookfrom, camera.lookat - camera.lookfrom + 0.4 * right);
+        let rec = world.hit(&ray, (0.001, f64::INFINITY)).unwrap();
+        assert!(rec.p.y > 0.5);
+    }
+
+    #[test]
+    fn loads_ply_meshes_next_to_the_scene() {
+        let directory = std::env::temp_dir().join(format!("pbrt-plymesh-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let ply = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+        fs::write(directory.join("triangle.ply"), ply).unwrap();
+        fs::write(directory.join("scene.pbrt"), "WorldBegin\nShape \"plymesh\" \"string filename\" \"triangle.ply\"\n").unwrap();
+
+        let (world, _, warnings) = load(&directory.join("scene.pbrt")).unwrap();
+        assert!(warnings.is_empty());
+        assert!(world.hit(&Ray::new(Point3::new(-0.25, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0)), (0.001, f64::INFINITY)).is_some());
+
+        let Err(Error::SceneFile(errors)) = parse("Shape \"plymesh\" \"string filename\" \"missing.ply\"") else { panic!("expected an error") };
+        assert!(errors[0].message.starts_with("missing.ply: "));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn reports_errors_with_lines() {
+        let text = "WorldBegin\nAttributeEnd\nNamedMaterial \"missing\"\nShape \"trianglemesh\" \"point3 P\" [0 0 0 1 0 0]\nTranslate 1 2\n";
+        let Err(Error::SceneFile(errors)) = parse(text) else { panic!("expected errors") };
+        let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(messages, [
+            "line 2: AttributeEnd: AttributeEnd without a matching begin",
+            "line 3: NamedMaterial: unknown material \"missing\"",
+            "line 4: Shape: missing \"integer indices\"",
+            "line 5: Translate: expected 3 numbers, found 2"
+        ]);
+
+        assert!(matches!(parse("Shape \"sphere\n"), Err(Error::SceneFile(errors)) if errors[0].message == "unterminated string"));
+    }
+}
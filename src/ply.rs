@@ -0,0 +1,423 @@
+use crate::color::{srgb_to_linear, Color};
+use crate::mesh::{invalid, MeshData, MAX_RESERVED};
+use crate::vec3::{Point3, Vec3};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Reads the PLY file at `path`. See [`read`].
+pub fn load(path: &Path) -> io::Result<MeshData> {
+    read(BufReader::new(File::open(path)?))
+}
+
+/// Reads an ASCII or binary PLY mesh as it streams in from `reader`. Vertices give their
+/// position in `x`, `y` and `z`, and may give a normal in `nx`, `ny` and `nz` and an sRGB
+/// color in `red`, `green` and `blue`. Faces list their vertices in `vertex_indices`, and
+/// polygons are split into triangles. Other elements and properties are skipped.
+pub fn read(mut reader: impl BufRead) -> io::Result<MeshData> {
+    let (format, elements) = read_header(&mut reader)?;
+    let mut body = Body { reader, format, line: String::new(), pos: 0 };
+    let mut mesh = MeshData::default();
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut body, element, &mut mesh)?,
+            "face" => read_faces(&mut body, element, &mut mesh)?,
+            _ => {
+                for _ in 0..element.count {
+                    body.begin()?;
+                    for (_, property) in &element.properties {
+                        body.skip(*property)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(mesh)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8
+        }
+    }
+
+    /// The largest value of an integer type, which stands for full intensity in colors.
+    fn full_scale(self) -> f64 {
+        match self {
+            Scalar::I8 => i8::MAX as f64,
+            Scalar::U8 => u8::MAX as f64,
+            Scalar::I16 => i16::MAX as f64,
+            Scalar::U16 => u16::MAX as f64,
+            Scalar::I32 => i32::MAX as f64,
+            Scalar::U32 => u32::MAX as f64,
+            Scalar::F32 | Scalar::F64 => 1.0
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Property {
+    Scalar(Scalar),
+    List { count: Scalar, item: Scalar }
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<(String, Property)>
+}
+
+fn read_header(reader: &mut impl BufRead) -> io::Result<(Format, Vec<Element>)> {
+    let mut line = String::new();
+    let mut next_line = |line: &mut String| -> io::Result<()> {
+        line.clear();
+        if reader.read_line(line)? == 0 {
+            return Err(invalid("PLY header ends before end_header"));
+        }
+        Ok(())
+    };
+
+    next_line(&mut line)?;
+    if line.trim_end() != "ply" {
+        return Err(invalid("not a PLY file"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    loop {
+        next_line(&mut line)?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            ["format", name, _] => {
+                format = Some(match name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::LittleEndian,
+                    "binary_big_endian" => Format::BigEndian,
+                    _ => return Err(invalid(format!("unknown PLY format {}", name)))
+                });
+            }
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| invalid(format!("invalid count of PLY element {}", name)))?;
+                elements.push(Element { name: name.to_string(), count, properties: vec![] });
+            }
+            ["property", "list", count, item, name] => {
+                let (Some(count), Some(item)) = (Scalar::parse(count), Scalar::parse(item)) else {
+                    return Err(invalid(format!("unknown type of PLY property {}", name)));
+                };
+                let element = elements.last_mut().ok_or_else(|| invalid("PLY property outside an element"))?;
+                element.properties.push((name.to_string(), Property::List { count, item }));
+            }
+            ["property", kind, name] => {
+                let kind = Scalar::parse(kind).ok_or_else(|| invalid(format!("unknown type of PLY property {}", name)))?;
+                let element = elements.last_mut().ok_or_else(|| invalid("PLY property outside an element"))?;
+                element.properties.push((name.to_string(), Property::Scalar(kind)));
+            }
+            _ => return Err(invalid(format!("unexpected PLY header line: {}", line.trim_end())))
+        }
+    }
+    Ok((format.ok_or_else(|| invalid("PLY header has no format"))?, elements))
+}
+
+/// The data following the header, read a value at a time.
+struct Body<R> {
+    reader: R,
+    format: Format,
+    /// The current ASCII element and how far into it reading has got.
+    line: String,
+    pos: usize
+}
+
+impl<R: BufRead> Body<R> {
+    /// Moves on to the next element. In ASCII files each is a line of its own.
+    fn begin(&mut self) -> io::Result<()> {
+        if self.format == Format::Ascii {
+            loop {
+                self.line.clear();
+                self.pos = 0;
+                if self.reader.read_line(&mut self.line)? == 0 {
+                    return Err(invalid("PLY file ends before all its elements"));
+                }
+                if !self.line.trim().is_empty() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn scalar(&mut self, kind: Scalar) -> io::Result<f64> {
+        if self.format == Format::Ascii {
+            let rest = &self.line[self.pos..];
+            let start = rest.len() - rest.trim_start().len();
+            let length = rest[start..].find(char::is_whitespace).unwrap_or(rest.len() - start);
+            if length == 0 {
+                return Err(invalid("PLY element has too few values"));
+            }
+            let token = &rest[start..start + length];
+            self.pos += start + length;
+            return token.parse().map_err(|_| invalid(format!("invalid PLY value {}", token)));
+        }
+
+        let mut bytes = [0u8; 8];
+        let bytes = &mut bytes[..kind.size()];
+        self.reader.read_exact(bytes)?;
+        let little = self.format == Format::LittleEndian;
+        macro_rules! decode {
+            ($type:ty) => {{
+                let bytes = (&*bytes).try_into().unwrap();
+                (if little { <$type>::from_le_bytes(bytes) } else { <$type>::from_be_bytes(bytes) }) as f64
+            }};
+        }
+        Ok(match kind {
+            Scalar::I8 => decode!(i8),
+            Scalar::U8 => decode!(u8),
+            Scalar::I16 => decode!(i16),
+            Scalar::U16 => decode!(u16),
+            Scalar::I32 => decode!(i32),
+            Scalar::U32 => decode!(u32),
+            Scalar::F32 => decode!(f32),
+            Scalar::F64 => decode!(f64)
+        })
+    }
+
+    /// Reads a list's length, which must be a count.
+    fn count(&mut self, kind: Scalar) -> io::Result<usize> {
+        let count = self.scalar(kind)?;
+        if count < 0.0 || count.fract() != 0.0 {
+            return Err(invalid(format!("invalid PLY list length {}", count)));
+        }
+        Ok(count as usize)
+    }
+
+    fn skip(&mut self, property: Property) -> io::Result<()> {
+        let (count, kind) = match property {
+            Property::Scalar(kind) => (1, kind),
+            Property::List { count, item } => (self.count(count)?, item)
+        };
+        if self.format == Format::Ascii {
+            for _ in 0..count {
+                self.scalar(kind)?;
+            }
+        } else {
+            let length = (count * kind.size()) as u64;
+            if io::copy(&mut (&mut self.reader).take(length), &mut io::sink())? < length {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a vertex property goes.
+#[derive(Clone, Copy, PartialEq)]
+enum Slot {
+    Position(usize),
+    Normal(usize),
+    Color(usize),
+    Skip
+}
+
+fn read_vertices(body: &mut Body<impl BufRead>, element: &Element, mesh: &mut MeshData) -> io::Result<()> {
+    let slots: Vec<(Slot, Property)> = element
+        .properties
+        .iter()
+        .map(|(name, property)| {
+            let slot = match (name.as_str(), property) {
+                (_, Property::List { .. }) => Slot::Skip,
+                ("x", _) => Slot::Position(0),
+                ("y", _) => Slot::Position(1),
+                ("z", _) => Slot::Position(2),
+                ("nx", _) => Slot::Normal(0),
+                ("ny", _) => Slot::Normal(1),
+                ("nz", _) => Slot::Normal(2),
+                ("red" | "diffuse_red", _) => Slot::Color(0),
+                ("green" | "diffuse_green", _) => Slot::Color(1),
+                ("blue" | "diffuse_blue", _) => Slot::Color(2),
+                _ => Slot::Skip
+            };
+            (slot, *property)
+        })
+        .collect();
+    let has = |slot: fn(usize) -> Slot| (0..3).all(|axis| slots.iter().any(|&(other, _)| other == slot(axis)));
+    if !has(Slot::Position) {
+        return Err(invalid("PLY vertices need x, y and z"));
+    }
+    let has_normals = has(Slot::Normal);
+    let has_colors = has(Slot::Color);
+
+    let reserved = element.count.min(MAX_RESERVED);
+    mesh.positions.reserve(reserved);
+    let mut normals = Vec::with_capacity(if has_normals { reserved } else { 0 });
+    let mut colors = Vec::with_capacity(if has_colors { reserved } else { 0 });
+    for _ in 0..element.count {
+        body.begin()?;
+        let (mut position, mut normal, mut color) = (Point3::zeroes(), Vec3::zeroes(), Color::zeroes());
+        for &(slot, property) in &slots {
+            let (Property::Scalar(kind), false) = (property, slot == Slot::Skip) else {
+                body.skip(property)?;
+                continue;
+            };
+            match slot {
+                Slot::Position(axis) => position[axis] = body.scalar(kind)?,
+                Slot::Normal(axis) => normal[axis] = body.scalar(kind)?,
+                Slot::Color(channel) => color[channel] = srgb_to_linear(body.scalar(kind)? / kind.full_scale()),
+                Slot::Skip => unreachable!()
+            }
+        }
+        mesh.positions.push(position);
+        if has_normals { normals.push(normal) };
+        if has_colors { colors.push(color) };
+    }
+    mesh.normals = has_normals.then_some(normals);
+    mesh.colors = has_colors.then_some(colors);
+    Ok(())
+}
+
+fn read_faces(body: &mut Body<impl BufRead>, element: &Element, mesh: &mut MeshData) -> io::Result<()> {
+    let indices = element
+        .properties
+        .iter()
+        .position(|(name, property)| matches!(property, Property::List { .. }) && (name == "vertex_indices" || name == "vertex_index"))
+        .ok_or_else(|| invalid("PLY faces need vertex_indices"))?;
+
+    mesh.triangles.reserve(element.count.min(MAX_RESERVED));
+    let mut polygon = vec![];
+    for _ in 0..element.count {
+        body.begin()?;
+        for (position, (_, property)) in element.properties.iter().enumerate() {
+            match *property {
+                Property::List { count, item } if position == indices => {
+                    let count = body.count(count)?;
+                    polygon.clear();
+                    for _ in 0..count {
+                        let index = body.scalar(item)?;
+                        if index < 0.0 || index.fract() != 0.0 || index > u32::MAX as f64 {
+                            return Err(invalid(format!("invalid PLY vertex index {}", index)));
+                        }
+                        polygon.push(index as u32);
+                    }
+                }
+                property => body.skip(property)?
+            }
+        }
+        // Fans are exact for the convex polygons that meshes are made of.
+        for k in 1..polygon.len().saturating_sub(1) {
+            mesh.triangles.push([polygon[0], polygon[k], polygon[k + 1]]);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment a unit square with a colored corner
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 0 0 1 255 0 0
+1 0 0 0 0 1 255 255 255
+1 1 0 0 0 1 255 255 255
+0 1 0 0 0 1 255 255 255
+4 0 1 2 3
+0 1
+";
+
+    #[test]
+    fn reads_ascii() {
+        let mesh = read(ASCII.as_bytes()).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.normals.unwrap()[2], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(mesh.colors.unwrap()[0], Color::new(1.0, 0.0, 0.0));
+    }
+
+    /// The binary form of a triangle with one vertex property to skip.
+    fn binary(little: bool) -> Vec<u8> {
+        let format = if little { "binary_little_endian" } else { "binary_big_endian" };
+        let mut data = format!(
+            "ply\nformat {} 1.0\nelement vertex 3\nproperty double x\nproperty double y\nproperty double z\nproperty ushort confidence\nelement face 1\nproperty list uchar uint vertex_indices\nend_header\n",
+            format
+        ).into_bytes();
+        for p in [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 2.0, -1.5f64]] {
+            for value in p {
+                data.extend(if little { value.to_le_bytes() } else { value.to_be_bytes() });
+            }
+            data.extend([0, 7]);
+        }
+        data.push(3);
+        for index in [0u32, 1, 2] {
+            data.extend(if little { index.to_le_bytes() } else { index.to_be_bytes() });
+        }
+        data
+    }
+
+    #[test]
+    fn reads_either_byte_order() {
+        for little in [true, false] {
+            let mesh = read(binary(little).as_slice()).unwrap();
+            assert_eq!(mesh.positions[2], Point3::new(0.0, 2.0, -1.5));
+            assert_eq!(mesh.triangles, [[0, 1, 2]]);
+            assert!(mesh.normals.is_none() && mesh.colors.is_none());
+        }
+
+        let truncated = binary(true);
+        assert_eq!(read(&truncated[..truncated.len() - 2]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(read(ASCII.replace("property float z\n", "").as_bytes()).unwrap_err().to_string(), "PLY vertices need x, y and z");
+    }
+}
@@ -0,0 +1,286 @@
+use crate::color::{Color, luminance};
+use crate::film::Film;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::f64::consts::PI;
+
+/// Lens and film effects applied to linear HDR pixels, before tonemapping and quantization.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    /// Adds the light above `threshold` luminance back in, blurred by Gaussians of `levels`
+    /// scales starting at `radius` pixels and doubling each time, up to the size of the image.
+    Bloom { threshold: f64, intensity: f64, radius: f64, levels: u32 },
+    /// Smears the light above `threshold` luminance into `streaks` evenly spaced rays that fade
+    /// over `length` pixels, reaching at most across the image, rotated by `rotation` degrees.
+    Glare { threshold: f64, intensity: f64, streaks: u32, length: f64, rotation: f64 },
+    /// Darkens toward the corners by `strength`, following the radius raised to `power`.
+    Vignette { strength: f64, power: f64 },
+    /// Scales the red channel outward and the blue channel inward from the image center by
+    /// `strength`, as a fraction of the distance to the center.
+    ChromaticAberration { strength: f64 },
+    /// Multiplies every pixel by Gaussian noise of standard deviation `amount`, repeatable for a
+    /// given `seed`.
+    Grain { amount: f64, seed: u64 }
+}
+
+impl Effect {
+    pub fn bloom() -> Effect {
+        Effect::Bloom { threshold: 1.0, intensity: 0.1, radius: 2.0, levels: 5 }
+    }
+
+    pub fn glare() -> Effect {
+        Effect::Glare { threshold: 2.0, intensity: 0.05, streaks: 4, length: 20.0, rotation: 45.0 }
+    }
+
+    pub fn vignette() -> Effect {
+        Effect::Vignette { strength: 0.4, power: 2.0 }
+    }
+
+    pub fn chromatic_aberration() -> Effect {
+        Effect::ChromaticAberration { strength: 0.002 }
+    }
+
+    pub fn grain() -> Effect {
+        Effect::Grain { amount: 0.03, seed: 0 }
+    }
+
+    /// Applies this effect to the resolved pixels of `film`, keeping its weights and AOV layers.
+    pub fn apply(&self, film: &Film) -> Film {
+        let image = Image { width: film.width, height: film.height, colors: film.to_colors() };
+        let colors = match *self {
+            Effect::Bloom { threshold, intensity, radius, levels } => bloom(&image, threshold, intensity, radius, levels),
+            Effect::Glare { threshold, intensity, streaks, length, rotation } => {
+                glare(&image, threshold, intensity, streaks, length, rotation)
+            }
+            Effect::Vignette { strength, power } => vignette(&image, strength, power),
+            Effect::ChromaticAberration { strength } => chromatic_aberration(&image, strength),
+            Effect::Grain { amount, seed } => grain(&image, amount, seed)
+        };
+        film.with_colors(colors)
+    }
+}
+
+/// An ordered chain of effects.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PostProcess {
+    pub effects: Vec<Effect>
+}
+
+impl PostProcess {
+    pub fn new(effects: Vec<Effect>) -> PostProcess {
+        PostProcess { effects }
+    }
+
+    pub fn apply(&self, film: &Film) -> Film {
+        self.effects.iter().fold(film.clone(), |film, effect| effect.apply(&film))
+    }
+}
+
+struct Image {
+    width: usize,
+    height: usize,
+    colors: Vec<Color>
+}
+
+impl Image {
+    fn get(&self, x: isize, y: isize) -> Color {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.colors[y * self.width + x]
+    }
+
+    /// Bilinear lookup at continuous pixel coordinates, clamped to the edges.
+    fn sample(&self, x: f64, y: f64) -> Color {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = (1.0 - fx) * self.get(x0, y0) + fx * self.get(x0 + 1, y0);
+        let bottom = (1.0 - fx) * self.get(x0, y0 + 1) + fx * self.get(x0 + 1, y0 + 1);
+        (1.0 - fy) * top + fy * bottom
+    }
+
+    fn map_pixels(&self, f: impl Fn(usize, usize) -> Color + Send + Sync) -> Vec<Color> {
+        (0..self.width * self.height).into_par_iter().map(|index| f(index % self.width, index / self.width)).collect()
+    }
+
+    /// The light above `threshold` luminance, keeping each pixel's hue.
+    fn bright_pass(&self, threshold: f64) -> Image {
+        let colors = self.colors.iter().map(|&color| {
+            let l = luminance(color);
+            if l > threshold { ((l - threshold) / l) * color } else { Color::zeroes() }
+        }).collect();
+        Image { width: self.width, height: self.height, colors }
+    }
+
+    fn gaussian_blur(&self, sigma: f64) -> Image {
+        let radius = (3.0 * sigma).ceil() as isize;
+        let kernel: Vec<f64> = (-radius..=radius).map(|d| (-(d * d) as f64 / (2.0 * sigma * sigma)).exp()).collect();
+        let total: f64 = kernel.iter().sum();
+        let blur = |image: &Image, dx: isize, dy: isize| Image {
+            width: image.width,
+            height: image.height,
+            colors: image.map_pixels(|x, y| {
+                kernel.iter().zip(-radius..=radius)
+                    .map(|(&weight, d)| weight * image.get(x as isize + d * dx, y as isize + d * dy))
+                    .fold(Color::zeroes(), |sum, color| sum + color) / total
+            })
+        };
+        blur(&blur(self, 1, 0), 0, 1)
+    }
+}
+
+fn bloom(image: &Image, threshold: f64, intensity: f64, radius: f64, levels: u32) -> Vec<Color> {
+    if levels == 0 || radius <= 0.0 || radius.is_nan() {
+        return image.colors.clone();
+    }
+    let bright = image.bright_pass(threshold);
+    let mut glow = vec![Color::zeroes(); image.colors.len()];
+    // Blurs wider than the image add nothing new, so the remaining levels repeat the widest.
+    let max_sigma = image.width.max(image.height) as f64;
+    let mut level = 0;
+    while level < levels {
+        let sigma = radius * 2f64.powi(level as i32);
+        let repeats = if sigma < max_sigma { 1 } else { levels - level };
+        let blurred = bright.gaussian_blur(sigma.min(max_sigma));
+        for (sum, color) in glow.iter_mut().zip(blurred.colors) {
+            *sum += repeats as f64 * color / levels as f64;
+        }
+        level += repeats;
+    }
+    image.colors.iter().zip(glow).map(|(&color, glow)| color + intensity * glow).collect()
+}
+
+fn glare(image: &Image, threshold: f64, intensity: f64, streaks: u32, length: f64, rotation: f64) -> Vec<Color> {
+    if length <= 0.0 || length.is_nan() {
+        return image.colors.clone();
+    }
+    let bright = image.bright_pass(threshold);
+    // Streaks longer than the image's diagonal reach nothing further.
+    let diagonal = (image.width as f64).hypot(image.height as f64);
+    let steps = (3.0 * length).min(diagonal).ceil().max(1.0) as usize;
+    let falloff: Vec<f64> = (1..=steps).map(|t| (-(t as f64) / length).exp()).collect();
+    let total: f64 = falloff.iter().sum();
+    let directions: Vec<(f64, f64)> = (0..streaks)
+        .map(|k| rotation.to_radians() + 2.0 * PI * k as f64 / streaks as f64)
+        .map(|angle| (angle.cos(), angle.sin()))
+        .collect();
+
+    image.map_pixels(|x, y| {
+        let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+        let mut streak = Color::zeroes();
+        for &(dx, dy) in &directions {
+            for (t, &weight) in falloff.iter().enumerate() {
+                let t = (t + 1) as f64;
+                streak += weight * bright.sample(px - t * dx, py - t * dy);
+            }
+        }
+        image.colors[y * image.width + x] + (intensity / total) * streak
+    })
+}
+
+fn vignette(image: &Image, strength: f64, power: f64) -> Vec<Color> {
+    let (cx, cy) = (0.5 * image.width as f64, 0.5 * image.height as f64);
+    let corner = (cx * cx + cy * cy).sqrt();
+    image.map_pixels(|x, y| {
+        let r = ((x as f64 + 0.5 - cx).powi(2) + (y as f64 + 0.5 - cy).powi(2)).sqrt() / corner;
+        (1.0 - strength * r.powf(power)).max(0.0) * image.colors[y * image.width + x]
+    })
+}
+
+fn chromatic_aberration(image: &Image, strength: f64) -> Vec<Color> {
+    let (cx, cy) = (0.5 * image.width as f64, 0.5 * image.height as f64);
+    image.map_pixels(|x, y| {
+        let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+        let red = image.sample(cx + dx / (1.0 + strength), cy + dy / (1.0 + strength)).x;
+        let blue = image.sample(cx + dx / (1.0 - strength), cy + dy / (1.0 - strength)).z;
+        Color::new(red, image.colors[y * image.width + x].y, blue)
+    })
+}
+
+fn grain(image: &Image, amount: f64, seed: u64) -> Vec<Color> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    image.colors.iter().map(|&color| {
+        let (u1, u2): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen());
+        let noise = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+        (1.0 + amount * noise).max(0.0) * color
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_light(size: usize, value: f64) -> Film {
+        let mut colors = vec![Color::zeroes(); size * size];
+        colors[size / 2 * size + size / 2] = value * Color::ones();
+        Film::from_colors(size, size, colors)
+    }
+
+    #[test]
+    fn bloom_spreads_highlights_only() {
+        let film = point_light(9, 10.0);
+        let bloomed = Effect::bloom().apply(&film);
+        assert!(bloomed.pixel(3, 4).x > 0.0);
+        assert!(bloomed.pixel(4, 4).x > 10.0);
+
+        let dim = point_light(9, 0.5);
+        assert_eq!(Effect::bloom().apply(&dim), dim);
+    }
+
+    #[test]
+    fn bloom_caps_blurs_at_image_size() {
+        let film = point_light(9, 10.0);
+        let wide = Effect::Bloom { threshold: 1.0, intensity: 0.1, radius: 1e9, levels: u32::MAX }.apply(&film);
+        let widest = Effect::Bloom { threshold: 1.0, intensity: 0.1, radius: 1e9, levels: 1 }.apply(&film);
+        assert!((wide.pixel(0, 0) - widest.pixel(0, 0)).length() < 1e-12);
+        assert!(wide.pixel(0, 0).x > 0.0);
+    }
+
+    #[test]
+    fn glare_follows_streaks() {
+        let film = point_light(15, 10.0);
+        let glared = Effect::Glare { threshold: 1.0, intensity: 1.0, streaks: 4, length: 3.0, rotation: 0.0 }.apply(&film);
+        assert!(glared.pixel(10, 7).x > 0.0);
+        assert!(glared.pixel(7, 3).x > 0.0);
+        assert_eq!(glared.pixel(10, 10), Color::zeroes());
+    }
+
+    #[test]
+    fn glare_needs_a_length() {
+        let film = point_light(15, 10.0);
+        let zero = Effect::Glare { threshold: 1.0, intensity: 1.0, streaks: 4, length: 0.0, rotation: 0.0 }.apply(&film);
+        assert_eq!(zero, film);
+
+        let long = Effect::Glare { threshold: 1.0, intensity: 1.0, streaks: 4, length: 1e9, rotation: 0.0 }.apply(&film);
+        assert!(long.pixels().all(|color| color.x.is_finite()));
+        assert!(long.pixel(14, 7).x > 0.0);
+    }
+
+    #[test]
+    fn vignette_darkens_corners() {
+        let film = Film::from_colors(4, 4, vec![Color::ones(); 16]);
+        let vignetted = Effect::vignette().apply(&film);
+        assert!(vignetted.pixel(0, 0).x < vignetted.pixel(1, 1).x);
+        assert!(vignetted.pixel(1, 1).x < 1.0);
+    }
+
+    #[test]
+    fn aberration_keeps_center_and_green() {
+        let colors = (0..25).map(|i| Color::new(i as f64, i as f64, i as f64)).collect();
+        let film = Film::from_colors(5, 5, colors);
+        let shifted = Effect::ChromaticAberration { strength: 0.2 }.apply(&film);
+        assert!((shifted.pixel(2, 2) - film.pixel(2, 2)).length() < 1e-12);
+        assert_eq!(shifted.pixel(0, 0).y, 0.0);
+        assert!(shifted.pixel(0, 0).x > 0.0);
+    }
+
+    #[test]
+    fn grain_is_repeatable() {
+        let film = Film::from_colors(4, 4, vec![Color::ones(); 16]);
+        let chain = PostProcess::new(vec![Effect::grain()]);
+        assert_eq!(chain.apply(&film), chain.apply(&film));
+        assert_ne!(chain.apply(&film), film);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::error::{Error, Result};
+use crate::film::Film;
+use crate::output::{save_as, ImageFormat};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Settings for progressive rendering.
+///
+/// The first pass takes `first_pass_samples` per pixel and each later pass doubles the
+/// accumulated count, until the camera's `samples_per_pixel` is reached. With an `interval`,
+/// intermediate images are only emitted once that much time has passed since the last one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progressive {
+    pub first_pass_samples: u32,
+    pub interval: Option<Duration>
+}
+
+impl Progressive {
+    pub fn new(first_pass_samples: u32, interval: Option<Duration>) -> Progressive {
+        Self::try_new(first_pass_samples, interval).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(first_pass_samples: u32, interval: Option<Duration>) -> Result<Progressive> {
+        if first_pass_samples == 0 { return Err(Error::invalid_value("First pass samples", "must be at least one")) };
+        Ok(Progressive { first_pass_samples, interval })
+    }
+}
+
+impl Default for Progressive {
+    fn default() -> Self {
+        Self::new(1, None)
+    }
+}
+
+/// Overwrites the image at `path` with `film` in `format`. The image is written to a sibling
+/// file first so viewers never see a half-written image.
+pub fn overwrite_image(path: &Path, film: &Film, format: ImageFormat) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+
+    save_as(Path::new(&partial), film, format)?;
+    fs::rename(&partial, path)
+}
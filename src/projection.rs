@@ -0,0 +1,139 @@
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// How the camera maps image positions to rays.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Projection {
+    /// Pinhole or thin-lens perspective with the camera's `vfov`.
+    #[default]
+    Perspective,
+    /// Parallel rays along the view direction, leaving a viewport `view_height` world units
+    /// tall centered on `lookfrom`. A defocus angle blurs everything away from `focus_dist`.
+    Orthographic { view_height: f64 },
+    /// Full 360° by 180° latitude-longitude panorama, centered on the view direction. Use an
+    /// aspect ratio of 2.
+    Equirectangular,
+    /// Circular fisheye covering `fov` degrees across the shorter image side.
+    Fisheye { fov: f64, mapping: FisheyeMapping },
+    /// Six 90° faces side by side in the order right, left, up, down, back, front, following
+    /// the OpenGL cubemap layout. Use an aspect ratio of 6 for square faces.
+    Cubemap
+}
+
+/// Relation between a fisheye's image radius and the angle from its axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FisheyeMapping {
+    /// Radius proportional to the angle.
+    #[default]
+    Equidistant,
+    /// Radius proportional to the sine of half the angle, preserving solid angles.
+    Equisolid
+}
+
+impl Projection {
+    /// Whether rays leave from a single point in directions given by `direction`, rather than
+    /// through the camera's viewport.
+    pub fn is_directional(&self) -> bool {
+        !matches!(self, Projection::Perspective | Projection::Orthographic { .. })
+    }
+
+    /// Direction of the ray through continuous image position (`x`, `y`) of a `width` by
+    /// `height` image, in the camera frame with x along `u`, y along `v` and z along `w`, so
+    /// the view direction is negative z. `None` for positions that see nothing, such as the
+    /// corners outside a fisheye's image circle. Only directional projections have one.
+    pub fn direction(&self, x: f64, y: f64, width: usize, height: usize) -> Option<Vec3> {
+        let (width, height) = (width as f64, height as f64);
+        match *self {
+            Projection::Perspective | Projection::Orthographic { .. } => None,
+            Projection::Equirectangular => {
+                let longitude = 2.0 * PI * (x / width - 0.5);
+                let latitude = PI * (0.5 - y / height);
+                Some(Vec3::new(
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    -latitude.cos() * longitude.cos()
+                ))
+            }
+            Projection::Fisheye { fov, mapping } => {
+                let half_size = 0.5 * f64::min(width, height);
+                let (nx, ny) = ((x - 0.5 * width) / half_size, (0.5 * height - y) / half_size);
+                let r = (nx * nx + ny * ny).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let half_fov = 0.5 * fov.to_radians();
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * half_fov,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (0.5 * half_fov).sin()).clamp(-1.0, 1.0).asin()
+                };
+                let (sx, sy) = if r > 0.0 { (nx / r, ny / r) } else { (0.0, 0.0) };
+                Some(Vec3::new(theta.sin() * sx, theta.sin() * sy, -theta.cos()))
+            }
+            Projection::Cubemap => {
+                let face_width = width / 6.0;
+                let face = ((x / face_width).floor() as usize).min(5);
+                let a = 2.0 * (x / face_width - face as f64) - 1.0;
+                let b = 2.0 * (y / height) - 1.0;
+                let direction = match face {
+                    0 => Vec3::new(1.0, -b, -a),
+                    1 => Vec3::new(-1.0, -b, a),
+                    2 => Vec3::new(a, 1.0, b),
+                    3 => Vec3::new(a, -1.0, -b),
+                    4 => Vec3::new(a, -b, 1.0),
+                    _ => Vec3::new(-a, -b, -1.0)
+                };
+                Some(direction.unit())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Option<Vec3>, expected: Vec3) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).length() < 1e-9, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn equirectangular() {
+        let projection = Projection::Equirectangular;
+        assert_near(projection.direction(100.0, 50.0, 200, 100), Vec3::new(0.0, 0.0, -1.0));
+        assert_near(projection.direction(150.0, 50.0, 200, 100), Vec3::new(1.0, 0.0, 0.0));
+        assert_near(projection.direction(0.0, 50.0, 200, 100), Vec3::new(0.0, 0.0, 1.0));
+        assert_near(projection.direction(100.0, 0.0, 200, 100), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn fisheye() {
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let projection = Projection::Fisheye { fov: 180.0, mapping };
+            assert_near(projection.direction(50.0, 50.0, 100, 100), Vec3::new(0.0, 0.0, -1.0));
+            assert_near(projection.direction(100.0, 50.0, 100, 100), Vec3::new(1.0, 0.0, 0.0));
+            assert_near(projection.direction(50.0, 0.0, 100, 100), Vec3::new(0.0, 1.0, 0.0));
+            assert_eq!(projection.direction(0.0, 0.0, 100, 100), None);
+        }
+
+        let equidistant = Projection::Fisheye { fov: 180.0, mapping: FisheyeMapping::Equidistant };
+        let half = equidistant.direction(75.0, 50.0, 100, 100).unwrap();
+        assert!((half.x - (PI / 4.0).sin()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cubemap_face_centers() {
+        let projection = Projection::Cubemap;
+        let expected = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0)
+        ];
+        for (face, expected) in expected.into_iter().enumerate() {
+            assert_near(projection.direction(100.0 * face as f64 + 50.0, 50.0, 600, 100), expected);
+        }
+    }
+}
@@ -0,0 +1,709 @@
+use crate::camera::CameraBuilder;
+use crate::error::{Error, Result};
+use crate::geometry::{Scene, Sphere};
+use crate::hittable::Hittable;
+use crate::material::{Dielectric, Lambertian, Material, Metal};
+use crate::mesh::TriangleMesh;
+use crate::ply;
+use crate::stl;
+use crate::texture::{Checker, SolidColor, Texture};
+use crate::transform::{Instance, Transform};
+use crate::vec3::Vec3;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use toml::Spanned;
+
+/// A problem with one part of a scene file, located by line and by the path of the field,
+/// such as `objects[3].radius`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneFileError {
+    pub line: usize,
+    pub path: String,
+    pub message: String
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "line {}: {}: {}", self.line, self.path, self.message)
+        }
+    }
+}
+
+/// Loads the TOML scene file at `path`, finding the mesh files it names next to it. See
+/// [`parse`] for the format.
+pub fn load(path: &Path) -> Result<(Scene, CameraBuilder)> {
+    import(&fs::read_to_string(path)?, path.parent().unwrap_or(Path::new("")))
+}
+
+/// Builds the scene and camera described by TOML `text`:
+///
+/// - `[render]` sets `image_width` or `image_height`, `aspect_ratio`, `samples_per_pixel`,
+///   `max_depth` and `seed`.
+/// - `[camera]` sets `lookfrom`, `lookat`, `vup`, `vfov`, `defocus_angle` and `focus_dist`.
+/// - `[textures.<name>]` defines a `solid` texture of a `color`, or a `checker` of cubes
+///   `scale` wide alternating between `even` and `odd`.
+/// - `[materials.<name>]` defines a `lambertian` with an `albedo`, a `metal` with an `albedo`
+///   and `fuzz`, or a `dielectric` with a `refraction_index`.
+/// - `[[objects]]` adds a `sphere` with a `center`, `radius` and `material`, or a `mesh` read
+///   from the PLY or STL `file` with a `material`. Either is optionally placed by a
+///   `transform` table that scales, then rotates by degrees about x, y and z in turn, then
+///   translates. Mesh files are found relative to the working directory, or next to the
+///   scene when it is read by [`load`].
+///
+/// Colors and points are arrays of three numbers. Wherever a texture or material is expected,
+/// either its name or an inline value can be given. Every invalid entry is reported, not just
+/// the first.
+pub fn parse(text: &str) -> Result<(Scene, CameraBuilder)> {
+    import(text, Path::new(""))
+}
+
+/// Like [`parse`], finding files named by the scene in `directory`.
+fn import(text: &str, directory: &Path) -> Result<(Scene, CameraBuilder)> {
+    let deserializer = toml::Deserializer::parse(text).map_err(|err| toml_error(text, String::new(), &err))?;
+    let file: SceneFile = serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = field_path(err.path());
+        toml_error(text, path, err.inner())
+    })?;
+    SceneBuilder::new(text, directory, &file).build()
+}
+
+fn toml_error(text: &str, path: String, err: &toml::de::Error) -> Error {
+    let line = err.span().map_or(1, |span| line_of(text, span.start));
+    Error::SceneFile(vec![SceneFileError { line, path, message: err.message().trim_end().to_string() }])
+}
+
+/// The dotted path to a field, leaving out the wrappers that record where values start.
+fn field_path(path: &serde_path_to_error::Path) -> String {
+    let path = path.to_string();
+    let segments: Vec<&str> = path.split('.').filter(|segment| !segment.starts_with("$__") && *segment != "?").collect();
+    segments.join(".").replace(".[", "[")
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default)]
+    render: RenderSettings,
+    #[serde(default)]
+    camera: CameraSettings,
+    #[serde(default)]
+    textures: BTreeMap<String, Spanned<TextureDesc>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDesc>>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RenderSettings {
+    image_width: Option<Spanned<usize>>,
+    image_height: Option<Spanned<usize>>,
+    aspect_ratio: Option<f64>,
+    samples_per_pixel: Option<u32>,
+    max_depth: Option<u32>,
+    seed: Option<u64>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CameraSettings {
+    lookfrom: Option<[f64; 3]>,
+    lookat: Option<[f64; 3]>,
+    vup: Option<[f64; 3]>,
+    vfov: Option<f64>,
+    defocus_angle: Option<f64>,
+    focus_dist: Option<f64>
+}
+
+// Entries hold the fields of every type they can take, rather than being tagged enums, so
+// that serde keeps track of the field path and position of every error. `SceneBuilder`
+// checks that the fields given suit the type.
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum TextureType {
+    Solid,
+    Checker
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureDesc {
+    #[serde(rename = "type")]
+    kind: TextureType,
+    color: Option<[f64; 3]>,
+    scale: Option<f64>,
+    even: Option<Ref<[f64; 3]>>,
+    odd: Option<Ref<[f64; 3]>>
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum MaterialType {
+    Lambertian,
+    Metal,
+    Dielectric
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDesc {
+    #[serde(rename = "type")]
+    kind: MaterialType,
+    albedo: Option<Ref<[f64; 3]>>,
+    fuzz: Option<Spanned<f64>>,
+    refraction_index: Option<Spanned<f64>>
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ObjectType {
+    Sphere,
+    Mesh
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectDesc {
+    #[serde(rename = "type")]
+    kind: ObjectType,
+    center: Option<[f64; 3]>,
+    radius: Option<Spanned<f64>>,
+    file: Option<Spanned<String>>,
+    material: Option<Ref<MaterialDesc>>,
+    transform: Option<Spanned<TransformDesc>>
+}
+
+/// The fields that make up an object of each type, once they are known to be given.
+enum Shape<'a> {
+    Sphere([f64; 3], &'a Spanned<f64>),
+    Mesh(&'a Spanned<String>)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct TransformDesc {
+    scale: Option<Scale>,
+    rotate: Option<[f64; 3]>,
+    translate: Option<[f64; 3]>
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a number or an array of three numbers")]
+enum Scale {
+    Uniform(f64),
+    PerAxis([f64; 3])
+}
+
+impl TransformDesc {
+    fn to_transform(&self) -> Transform {
+        let scale = match self.scale {
+            Some(Scale::Uniform(factor)) => Vec3::new(factor, factor, factor),
+            Some(Scale::PerAxis(factors)) => vec3(factors),
+            None => Vec3::ones()
+        };
+        let [x, y, z] = self.rotate.unwrap_or_default();
+        Transform::scale(scale)
+            .then(&Transform::rotate(Vec3::new(1.0, 0.0, 0.0), x))
+            .then(&Transform::rotate(Vec3::new(0.0, 1.0, 0.0), y))
+            .then(&Transform::rotate(Vec3::new(0.0, 0.0, 1.0), z))
+            .then(&Transform::translate(vec3(self.translate.unwrap_or_default())))
+    }
+}
+
+/// Either the name of something defined elsewhere in the file, or the value itself.
+enum Ref<T> {
+    Named(String),
+    Inline(T)
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Ref<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct RefVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for RefVisitor<T> {
+            type Value = Ref<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a name or an inline value")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> std::result::Result<Self::Value, E> {
+                Ok(Ref::Named(name.to_string()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> std::result::Result<Self::Value, A::Error> {
+                T::deserialize(SeqAccessDeserializer::new(seq)).map(Ref::Inline)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<Self::Value, A::Error> {
+                T::deserialize(MapAccessDeserializer::new(map)).map(Ref::Inline)
+            }
+        }
+
+        deserializer.deserialize_any(RefVisitor(PhantomData))
+    }
+}
+
+fn vec3([x, y, z]: [f64; 3]) -> Vec3 {
+    Vec3::new(x, y, z)
+}
+
+/// Turns a parsed scene file into objects, collecting an error for every entry that fails.
+struct SceneBuilder<'a> {
+    text: &'a str,
+    directory: &'a Path,
+    file: &'a SceneFile,
+    textures: HashMap<&'a str, Option<Arc<dyn Texture>>>,
+    materials: HashMap<&'a str, Arc<dyn Material>>,
+    errors: Vec<SceneFileError>
+}
+
+impl<'a> SceneBuilder<'a> {
+    fn new(text: &'a str, directory: &'a Path, file: &'a SceneFile) -> SceneBuilder<'a> {
+        SceneBuilder { text, directory, file, textures: HashMap::new(), materials: HashMap::new(), errors: vec![] }
+    }
+
+    fn error(&mut self, span: Range<usize>, path: String, message: impl ToString) {
+        let line = line_of(self.text, span.start);
+        self.errors.push(SceneFileError { line, path, message: message.to_string() });
+    }
+
+    fn build(mut self) -> Result<(Scene, CameraBuilder)> {
+        for name in self.file.textures.keys() {
+            self.texture(name, &mut vec![]);
+        }
+        for (name, desc) in &self.file.materials {
+            let path = format!("materials.{}", name);
+            if let Some(material) = self.material(desc.get_ref(), desc.span(), &path) {
+                self.materials.insert(name, material);
+            }
+        }
+
+        let mut world = Scene::new();
+        for (index, desc) in self.file.objects.iter().enumerate() {
+            let path = format!("objects[{}]", index);
+            if let Some(object) = self.object(desc.get_ref(), desc.span(), &path) {
+                world.add(object);
+            }
+        }
+        let camera = self.camera();
+
+        if self.errors.is_empty() {
+            Ok((world, camera))
+        } else {
+            self.errors.sort_by_key(|err| err.line);
+            Err(Error::SceneFile(self.errors))
+        }
+    }
+
+    /// The texture called `name`, built on first use. `visiting` holds the textures being
+    /// built further up, to catch checkers that contain themselves.
+    fn texture(&mut self, name: &'a str, visiting: &mut Vec<&'a str>) -> Option<Arc<dyn Texture>> {
+        if let Some(texture) = self.textures.get(name) {
+            return texture.clone();
+        }
+        let (name, desc) = self.file.textures.get_key_value(name)?;
+        let name = name.as_str();
+        let path = format!("textures.{}", name);
+        if visiting.contains(&name) {
+            self.error(desc.span(), path, "texture refers to itself");
+            return None;
+        }
+
+        let span = desc.span();
+        let desc = desc.get_ref();
+        let texture: Option<Arc<dyn Texture>> = match desc.kind {
+            TextureType::Solid => {
+                let fields = [("scale", desc.scale.is_some()), ("even", desc.even.is_some()), ("odd", desc.odd.is_some())];
+                self.unused(&span, &path, "solid textures", &fields);
+                let color = self.required(desc.color, &span, &path, "color")?;
+                Some(Arc::new(SolidColor::new(vec3(color))))
+            }
+            TextureType::Checker => {
+                self.unused(&span, &path, "checker textures", &[("color", desc.color.is_some())]);
+                let scale = self.required(desc.scale, &span, &path, "scale");
+                if scale.is_some_and(|scale| scale <= 0.0) {
+                    self.error(span.clone(), format!("{}.scale", path), "must be positive");
+                }
+                let even = self.required(desc.even.as_ref(), &span, &path, "even");
+                let odd = self.required(desc.odd.as_ref(), &span, &path, "odd");
+                visiting.push(name);
+                let even = even.and_then(|even| self.texture_ref(even, span.clone(), &format!("{}.even", path), visiting));
+                let odd = odd.and_then(|odd| self.texture_ref(odd, span.clone(), &format!("{}.odd", path), visiting));
+                visiting.pop();
+                match (scale, even, odd) {
+                    (Some(scale), Some(even), Some(odd)) if scale > 0.0 => Some(Arc::new(Checker::new(scale, even, odd))),
+                    _ => None
+                }
+            }
+        };
+        // Failures are remembered too, so each is reported once however often it is used.
+        self.textures.insert(name, texture.clone());
+        texture
+    }
+
+    fn texture_ref(
+        &mut self,
+        texture: &'a Ref<[f64; 3]>,
+        span: Range<usize>,
+        path: &str,
+        visiting: &mut Vec<&'a str>
+    ) -> Option<Arc<dyn Texture>> {
+        match texture {
+            Ref::Inline(color) => Some(Arc::new(SolidColor::new(vec3(*color)))),
+            Ref::Named(name) if self.file.textures.contains_key(name.as_str()) => self.texture(name, visiting),
+            Ref::Named(name) => {
+                self.error(span, path.to_string(), format!("unknown texture `{}`", name));
+                None
+            }
+        }
+    }
+
+    fn material(&mut self, desc: &'a MaterialDesc, span: Range<usize>, path: &str) -> Option<Arc<dyn Material>> {
+        match desc.kind {
+            MaterialType::Lambertian => {
+                let fields = [("fuzz", desc.fuzz.is_some()), ("refraction_index", desc.refraction_index.is_some())];
+                self.unused(&span, path, "lambertian materials", &fields);
+                let albedo = self.required(desc.albedo.as_ref(), &span, path, "albedo")?;
+                let texture = self.texture_ref(albedo, span.clone(), &format!("{}.albedo", path), &mut vec![])?;
+                Some(Arc::new(Lambertian::from_texture(texture)))
+            }
+            MaterialType::Metal => {
+                self.unused(&span, path, "metal materials", &[("refraction_index", desc.refraction_index.is_some())]);
+                let albedo = match self.required(desc.albedo.as_ref(), &span, path, "albedo")? {
+                    Ref::Inline(albedo) => *albedo,
+                    Ref::Named(_) => {
+                        self.error(span, format!("{}.albedo", path), "must be a color; metals do not take textures");
+                        return None;
+                    }
+                };
+                let fuzz = desc.fuzz.as_ref();
+                let metal = Metal::try_new(vec3(albedo), fuzz.map_or(0.0, |fuzz| *fuzz.get_ref()));
+                let span = fuzz.map_or(span, |fuzz| fuzz.span());
+                self.checked(metal, span, format!("{}.fuzz", path)).map(|metal| Arc::new(metal) as _)
+            }
+            MaterialType::Dielectric => {
+                let fields = [("albedo", desc.albedo.is_some()), ("fuzz", desc.fuzz.is_some())];
+                self.unused(&span, path, "dielectric materials", &fields);
+                let refraction_index = self.required(desc.refraction_index.as_ref(), &span, path, "refraction_index")?;
+                let glass = Dielectric::try_new(*refraction_index.get_ref());
+                self.checked(glass, refraction_index.span(), format!("{}.refraction_index", path)).map(|glass| Arc::new(glass) as _)
+            }
+        }
+    }
+
+    fn object(&mut self, desc: &'a ObjectDesc, span: Range<usize>, path: &str) -> Option<Box<dyn Hittable>> {
+        let shape = match desc.kind {
+            ObjectType::Sphere => {
+                self.unused(&span, path, "spheres", &[("file", desc.file.is_some())]);
+                let center = self.required(desc.center, &span, path, "center");
+                let radius = self.required(desc.radius.as_ref(), &span, path, "radius");
+                center.zip(radius).map(|(center, radius)| Shape::Sphere(center, radius))
+            }
+            ObjectType::Mesh => {
+                let fields = [("center", desc.center.is_some()), ("radius", desc.radius.is_some())];
+                self.unused(&span, path, "meshes", &fields);
+                self.required(desc.file.as_ref(), &span, path, "file").map(Shape::Mesh)
+            }
+        };
+        let material = match self.required(desc.material.as_ref(), &span, path, "material")? {
+            Ref::Inline(material) => self.material(material, span.clone(), &format!("{}.material", path))?,
+            Ref::Named(name) => match self.materials.get(name.as_str()) {
+                Some(material) => material.clone(),
+                None => {
+                    // Materials that failed to build were reported already.
+                    if !self.file.materials.contains_key(name.as_str()) {
+                        self.error(span, format!("{}.material", path), format!("unknown material `{}`", name));
+                    }
+                    return None;
+                }
+            }
+        };
+
+        let object: Box<dyn Hittable> = match shape? {
+            Shape::Sphere(center, radius) => {
+                let sphere = Sphere::try_new(vec3(center), *radius.get_ref(), material);
+                Box::new(self.checked(sphere, radius.span(), format!("{}.radius", path))?)
+            }
+            Shape::Mesh(file) => Box::new(self.mesh(file, material, &format!("{}.file", path))?)
+        };
+        match &desc.transform {
+            Some(transform) => {
+                let instance = Instance::try_new(object, transform.get_ref().to_transform());
+                self.checked(instance, transform.span(), format!("{}.transform", path)).map(|instance| Box::new(instance) as _)
+            }
+            None => Some(object)
+        }
+    }
+
+    /// The PLY or STL mesh named by `file`, chosen by its extension.
+    fn mesh(&mut self, file: &Spanned<String>, material: Arc<dyn Material>, path: &str) -> Option<TriangleMesh> {
+        let name = Path::new(file.get_ref());
+        let data = match name.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("ply") => ply::load(&self.directory.join(name)),
+            Some("stl") => stl::load(&self.directory.join(name)),
+            _ => {
+                self.error(file.span(), path.to_string(), "must name a .ply or .stl file");
+                return None;
+            }
+        };
+        match data {
+            Ok(data) => self.checked(data.into_mesh(material), file.span(), path.to_string()),
+            Err(err) => {
+                self.error(file.span(), path.to_string(), format!("{}: {}", file.get_ref(), err));
+                None
+            }
+        }
+    }
+
+    /// The value of `result`, reporting its error at the field with `path` whose value is at `span`.
+    fn checked<T>(&mut self, result: Result<T>, span: Range<usize>, path: String) -> Option<T> {
+        result.map_err(|err| self.error(span, path, err)).ok()
+    }
+
+    /// The value of a field that entries of this type need, reporting it if it was left out.
+    fn required<T>(&mut self, value: Option<T>, span: &Range<usize>, path: &str, field: &str) -> Option<T> {
+        if value.is_none() {
+            self.error(span.clone(), path.to_string(), format!("missing field `{}`", field));
+        }
+        value
+    }
+
+    /// Reports the `fields` that were given although they mean nothing for `kind`.
+    fn unused(&mut self, span: &Range<usize>, path: &str, kind: &str, fields: &[(&str, bool)]) {
+        for &(field, given) in fields {
+            if given {
+                self.error(span.clone(), format!("{}.{}", path, field), format!("does not apply to {}", kind));
+            }
+        }
+    }
+
+    fn camera(&mut self) -> CameraBuilder {
+        let render = &self.file.render;
+        let camera = &self.file.camera;
+        let mut builder = CameraBuilder::new();
+
+        if let Some(aspect_ratio) = render.aspect_ratio { builder = builder.aspect_ratio(aspect_ratio) };
+        match (&render.image_width, &render.image_height) {
+            (Some(width), Some(height)) => {
+                let start = width.span().start.min(height.span().start);
+                let span = start..start;
+                self.error(span, "render".to_string(), "give image_width or image_height, not both");
+            }
+            (Some(width), None) => builder = builder.image_width(*width.get_ref()),
+            (None, Some(height)) => builder = builder.image_height(*height.get_ref()),
+            (None, None) => {}
+        }
+        if let Some(samples_per_pixel) = render.samples_per_pixel { builder = builder.samples_per_pixel(samples_per_pixel) };
+        if let Some(max_depth) = render.max_depth { builder = builder.max_depth(max_depth) };
+        if let Some(seed) = render.seed { builder = builder.seed(seed) };
+
+        if let Some(lookfrom) = camera.lookfrom { builder = builder.lookfrom(vec3(lookfrom)) };
+        if let Some(lookat) = camera.lookat { builder = builder.lookat(vec3(lookat)) };
+        if let Some(vup) = camera.vup { builder = builder.vup(vec3(vup)) };
+        if let Some(vfov) = camera.vfov { builder = builder.vfov(vfov) };
+        if let Some(defocus_angle) = camera.defocus_angle { builder = builder.defocus_angle(defocus_angle) };
+        if let Some(focus_dist) = camera.focus_dist { builder = builder.focus_dist(focus_dist) };
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::scenes::{random_sphere_layout, SphereMaterial};
+    use crate::vec3::Point3;
+
+    const SCENE: &str = r#"
+[render]
+image_width = 40
+aspect_ratio = 2.0
+samples_per_pixel = 4
+
+[camera]
+lookfrom = [0, 0, 0]
+lookat = [0, 0, -1]
+
+[textures.checker]
+type = "checker"
+scale = 0.5
+even = [0.2, 0.3, 0.1]
+odd = "white"
+
+[textures.white]
+type = "solid"
+color = [0.9, 0.9, 0.9]
+
+[materials.ground]
+type = "lambertian"
+albedo = "checker"
+
+[[objects]]
+type = "sphere"
+center = [0, -100.5, -1]
+radius = 100
+material = "ground"
+
+[[objects]]
+type = "sphere"
+center = [0, 0, 0]
+radius = 0.5
+material = { type = "metal", albedo = [0.8, 0.8, 0.8], fuzz = 0.1 }
+transform = { scale = [1, 2, 1], translate = [0, 0, -2] }
+"#;
+
+    fn load_errors(text: &str) -> Vec<SceneFileError> {
+        match parse(text) {
+            Err(Error::SceneFile(errors)) => errors,
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("scene should not load")
+        }
+    }
+
+    #[test]
+    fn builds_scene_and_camera() {
+        let (world, camera) = parse(SCENE).unwrap();
+        assert_eq!(world.objects.len(), 2);
+        let camera = camera.build().unwrap();
+        assert_eq!((camera.image_width(), camera.image_height, camera.samples_per_pixel), (40, 20, 4));
+
+        let rec = world.hit(&Ray::new(Point3::new(0.0, 5.0, -2.0), Vec3::new(0.0, -1.0, 0.0)), (0.001, f64::INFINITY)).unwrap();
+        assert!((rec.p.y - 1.0).abs() < 1e-9);
+    }
+
+    /// `x` as the example files write it: whole numbers without a fraction, and tiny or huge
+    /// ones in exponent form.
+    fn toml_number(x: f64) -> String {
+        if x.fract() == 0.0 { format!("{}", x) } else { format!("{:?}", x) }
+    }
+
+    fn toml_vec3(v: Vec3) -> String {
+        format!("[{}, {}, {}]", toml_number(v.x), toml_number(v.y), toml_number(v.z))
+    }
+
+    /// The materials and objects of `scenes/random_spheres.toml`, written out from the layout
+    /// `random_spheres` builds.
+    fn random_spheres_toml(seed: u64) -> String {
+        let layout = random_sphere_layout(seed);
+        let SphereMaterial::Lambertian(ground) = layout[0].2 else { panic!("ground should be lambertian") };
+        let mut text = format!("[materials.ground]\ntype = \"lambertian\"\nalbedo = {}\n\n", toml_vec3(ground));
+        text += "[materials.glass]\ntype = \"dielectric\"\nrefraction_index = 1.5\n";
+        for (index, (center, radius, material)) in layout.into_iter().enumerate() {
+            let material = match material {
+                _ if index == 0 => "\"ground\"".to_string(),
+                SphereMaterial::Dielectric(1.5) => "\"glass\"".to_string(),
+                SphereMaterial::Lambertian(albedo) => format!("{{ type = \"lambertian\", albedo = {} }}", toml_vec3(albedo)),
+                SphereMaterial::Metal(albedo, fuzz) => format!("{{ type = \"metal\", albedo = {}, fuzz = {} }}", toml_vec3(albedo), toml_number(fuzz)),
+                SphereMaterial::Dielectric(refraction_index) => format!("{{ type = \"dielectric\", refraction_index = {} }}", toml_number(refraction_index))
+            };
+            text += &format!("\n[[objects]]\ntype = \"sphere\"\ncenter = {}\nradius = {}\nmaterial = {}\n", toml_vec3(center), toml_number(radius), material);
+        }
+        text
+    }
+
+    #[test]
+    fn example_matches_random_spheres() {
+        let text = include_str!("../scenes/random_spheres.toml");
+        let (world, camera) = parse(text).unwrap();
+        let (expected, _) = crate::scenes::random_spheres(0);
+        assert_eq!(world.objects.len(), expected.objects.len());
+        assert_eq!(camera.build().unwrap().image_height, 720);
+
+        let start = text.find("[materials.ground]").unwrap();
+        assert!(text[start..] == random_spheres_toml(0), "scenes/random_spheres.toml no longer matches random_spheres(0)");
+    }
+
+    #[test]
+    fn loads_meshes_next_to_the_scene() {
+        let directory = std::env::temp_dir().join(format!("scene-file-mesh-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let ply = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+        fs::write(directory.join("triangle.ply"), ply).unwrap();
+        let stl = "solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        fs::write(directory.join("triangle.STL"), stl).unwrap();
+        let scene = "[[objects]]\ntype = \"mesh\"\nfile = \"triangle.ply\"\nmaterial = { type = \"lambertian\", albedo = [0.5, 0.5, 0.5] }\n\n\
+            [[objects]]\ntype = \"mesh\"\nfile = \"triangle.STL\"\nmaterial = { type = \"dielectric\", refraction_index = 1.5 }\ntransform = { translate = [0, 0, -2] }\n";
+        fs::write(directory.join("scene.toml"), scene).unwrap();
+
+        let (world, _) = load(&directory.join("scene.toml")).unwrap();
+        assert_eq!(world.objects.len(), 2);
+        let rec = world.hit(&Ray::new(Point3::new(0.25, 0.25, -3.0), Vec3::new(0.0, 0.0, 1.0)), (0.001, f64::INFINITY)).unwrap();
+        assert!((rec.p.z + 2.0).abs() < 1e-12);
+
+        let errors = load_errors(&scene.replace("triangle.ply", "missing.ply"));
+        let paths: Vec<&str> = errors.iter().map(|err| err.path.as_str()).collect();
+        assert_eq!(paths, ["objects[0].file", "objects[1].file"]);
+        assert!(errors[0].message.starts_with("missing.ply: "));
+        assert_eq!((errors[0].line, errors[1].line), (3, 8));
+
+        let errors = load_errors("[[objects]]\ntype = \"mesh\"\nfile = \"triangle.obj\"\nradius = 1\nmaterial = { type = \"dielectric\", refraction_index = 1.5 }\n");
+        let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(messages, [
+            "line 1: objects[0].radius: does not apply to meshes",
+            "line 3: objects[0].file: must name a .ply or .stl file"
+        ]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn syntax_errors_give_line_and_path() {
+        let errors = load_errors("[render]\nsamples_per_pixel = 4\n\n[[objects]]\ntype = \"sphere\"\ncenter = [0, 0]\nradius = 1\nmaterial = \"x\"\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].path.as_str()), (6, "objects[0].center"));
+
+        let errors = load_errors("[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = { type = \"metal\", fuzz = \"a lot\" }\n");
+        assert_eq!((errors[0].line, errors[0].path.as_str()), (5, "objects[0].material.fuzz"));
+
+        let errors = load_errors("[render]\nsamples_per_pixel = \"many\"\n");
+        assert_eq!((errors[0].line, errors[0].path.as_str()), (2, "render.samples_per_pixel"));
+
+        let errors = load_errors("[camera]\nfov = 20\n");
+        assert_eq!(errors[0].line, 2);
+        assert!(errors[0].message.contains("fov"));
+    }
+
+    #[test]
+    fn reports_every_invalid_entry() {
+        let text = SCENE.replace("radius = 0.5", "radius = -0.5").replace("odd = \"white\"", "odd = \"black\"");
+        let errors = load_errors(&text);
+        let paths: Vec<&str> = errors.iter().map(|err| err.path.as_str()).collect();
+        assert_eq!(paths, ["textures.checker.odd", "objects[1].radius"]);
+        assert!(errors[1].message.contains("Sphere radius"));
+        assert_eq!(text.lines().nth(errors[1].line - 1).unwrap(), "radius = -0.5");
+
+        let text = SCENE.replace("fuzz = 0.1", "fuzz = 2").replace("scale = [1, 2, 1]", "scale = 0");
+        let errors = load_errors(&text);
+        let paths: Vec<&str> = errors.iter().map(|err| err.path.as_str()).collect();
+        assert_eq!(paths, ["objects[1].material.fuzz"]);
+        let errors = load_errors(&text.replace("fuzz = 2", "fuzz = 0.1"));
+        assert_eq!(errors[0].path, "objects[1].transform");
+        assert!(text.lines().nth(errors[0].line - 1).unwrap().starts_with("transform"));
+
+        let errors = load_errors("[materials.glass]\ntype = \"dielectric\"\nfuzz = 0.1\n\n[[objects]]\ntype = \"sphere\"\nmaterial = \"glass\"\n");
+        let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(messages, [
+            "line 1: materials.glass.fuzz: does not apply to dielectric materials",
+            "line 1: materials.glass: missing field `refraction_index`",
+            "line 5: objects[0]: missing field `center`",
+            "line 5: objects[0]: missing field `radius`"
+        ]);
+    }
+}
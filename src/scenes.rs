@@ -0,0 +1,110 @@
+use crate::camera::CameraBuilder;
+use crate::color::Color;
+use crate::geometry::{Scene, Sphere};
+use crate::material::{Dielectric, Lambertian, Material, Metal};
+use crate::utils::{random_double, random_range, seed_random};
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+/// The book's final scene: hundreds of small random spheres around three large ones. The small
+/// spheres are placed from `seed`, so the same seed always gives the same scene.
+pub fn random_spheres(seed: u64) -> (Scene, CameraBuilder) {
+    let mut world = Scene::new();
+    for (center, radius, material) in random_sphere_layout(seed) {
+        world.add(Box::new(Sphere::new(center, radius, material.build())));
+    }
+
+    let camera = CameraBuilder::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_height(720)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .lookfrom(Point3::new(13.0, 2.0, 3.0))
+        .lookat(Point3::new(0.0, 0.0, 0.0))
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .defocus_angle(0.6)
+        .focus_dist(10.0);
+
+    (world, camera)
+}
+
+/// The book's defocus blur scene: a diffuse, a hollow glass and a fuzzy metal sphere on a
+/// yellow ground.
+pub fn three_spheres() -> (Scene, CameraBuilder) {
+    let mut world = Scene::new();
+
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_center = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+    let material_left = Arc::new(Dielectric::new(1.5));
+    let material_bubble = Arc::new(Dielectric::new(1.0 / 1.5));
+    let material_right = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 1.0));
+
+    world.add(Box::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, material_ground)));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -1.2), 0.5, material_center)));
+    world.add(Box::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.5, material_left)));
+    world.add(Box::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.4, material_bubble)));
+    world.add(Box::new(Sphere::new(Point3::new(1.0, 0.0, -1.0), 0.5, material_right)));
+
+    let camera = CameraBuilder::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .lookfrom(Point3::new(-2.0, 2.0, 1.0))
+        .lookat(Point3::new(0.0, 0.0, -1.0))
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .defocus_angle(10.0)
+        .focus_dist(3.4);
+
+    (world, camera)
+}
+
+/// Material of one of the spheres in [`random_spheres`], as the values it is built from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SphereMaterial {
+    Lambertian(Color),
+    Metal(Color, f64),
+    Dielectric(f64)
+}
+
+impl SphereMaterial {
+    fn build(self) -> Arc<dyn Material> {
+        match self {
+            SphereMaterial::Lambertian(albedo) => Arc::new(Lambertian::new(albedo)),
+            SphereMaterial::Metal(albedo, fuzz) => Arc::new(Metal::new(albedo, fuzz)),
+            SphereMaterial::Dielectric(refraction_index) => Arc::new(Dielectric::new(refraction_index))
+        }
+    }
+}
+
+/// Center, radius and material of every sphere in [`random_spheres`], in the order they are added.
+pub(crate) fn random_sphere_layout(seed: u64) -> Vec<(Point3, f64, SphereMaterial)> {
+    seed_random(seed);
+    let mut spheres = vec![(Point3::new(0.0, -1000.0, 0.0), 1000.0, SphereMaterial::Lambertian(Color::new(0.5, 0.5, 0.5)))];
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random_double();
+            let center = Point3::new(a as f64 + 0.9 * random_double(), 0.2, b as f64 + 0.9 * random_double());
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                let material = match choose_mat {
+                    mat if mat < 0.8 => SphereMaterial::Lambertian(Color::random() * Color::random()),
+                    mat if mat < 0.95 => {
+                        let albedo = Color::random_range(0.5, 1.0);
+                        SphereMaterial::Metal(albedo, random_range(0.0, 0.5))
+                    }
+                    _ => SphereMaterial::Dielectric(1.5)
+                };
+                spheres.push((center, 0.2, material));
+            }
+        }
+    }
+
+    spheres.push((Point3::new(0.0, 1.0, 0.0), 1.0, SphereMaterial::Dielectric(1.5)));
+    spheres.push((Point3::new(-4.0, 1.0, 0.0), 1.0, SphereMaterial::Lambertian(Color::new(0.4, 0.2, 0.1))));
+    spheres.push((Point3::new(4.0, 1.0, 0.0), 1.0, SphereMaterial::Metal(Color::new(0.7, 0.6, 0.5), 0.0)));
+    spheres
+}
@@ -0,0 +1,90 @@
+use crate::error::{Error, Result};
+
+/// Arrangement of the two eye views within one image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StereoLayout {
+    /// Left eye in the left half, right eye in the right half.
+    #[default]
+    SideBySide,
+    /// Left eye in the top half, right eye in the bottom half.
+    TopBottom
+}
+
+/// Renders a stereo pair into one image. Perspective eyes use parallel, off-axis frusta that
+/// agree at `convergence` distance, so objects there have zero parallax. With an
+/// equirectangular projection this gives an omni-directional stereo (ODS) panorama, whose eyes
+/// sit on a circle of diameter `interocular` and look along its tangents. Reconstruction
+/// filters wider than a pixel blend slightly across the seam between the two views.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    pub layout: StereoLayout,
+    pub interocular: f64,
+    pub convergence: f64
+}
+
+impl Stereo {
+    pub fn new(layout: StereoLayout, interocular: f64, convergence: f64) -> Stereo {
+        Self::try_new(layout, interocular, convergence).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(layout: StereoLayout, interocular: f64, convergence: f64) -> Result<Stereo> {
+        if interocular < 0.0 { return Err(Error::invalid_value("Interocular distance", "must not be negative")) };
+        if convergence <= 0.0 { return Err(Error::invalid_value("Convergence distance", "must be positive")) };
+        Ok(Stereo { layout, interocular, convergence })
+    }
+
+    /// Size of each eye's view within a `width` by `height` image.
+    pub fn eye_size(&self, width: usize, height: usize) -> (usize, usize) {
+        match self.layout {
+            StereoLayout::SideBySide => ((width / 2).max(1), height),
+            StereoLayout::TopBottom => (width, (height / 2).max(1))
+        }
+    }
+
+    /// Whether a `width` by `height` image divides into two eye views of the same size.
+    pub fn splits_evenly(&self, width: usize, height: usize) -> bool {
+        match self.layout {
+            StereoLayout::SideBySide => width.is_multiple_of(2),
+            StereoLayout::TopBottom => height.is_multiple_of(2)
+        }
+    }
+
+    /// Which eye pixel (`i`, `j`) belongs to, as -1 for left and 1 for right, and its position
+    /// within that eye's view.
+    pub fn eye_pixel(&self, i: usize, j: usize, width: usize, height: usize) -> (f64, usize, usize) {
+        let (eye_width, eye_height) = self.eye_size(width, height);
+        match self.layout {
+            StereoLayout::SideBySide if i >= eye_width => (1.0, i - eye_width, j),
+            StereoLayout::TopBottom if j >= eye_height => (1.0, i, j - eye_height),
+            _ => (-1.0, i, j)
+        }
+    }
+}
+
+impl Default for Stereo {
+    fn default() -> Self {
+        Stereo::new(StereoLayout::default(), 0.064, 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eye_pixels() {
+        let side_by_side = Stereo::default();
+        assert_eq!(side_by_side.eye_size(200, 100), (100, 100));
+        assert_eq!(side_by_side.eye_pixel(99, 10, 200, 100), (-1.0, 99, 10));
+        assert_eq!(side_by_side.eye_pixel(100, 10, 200, 100), (1.0, 0, 10));
+
+        let top_bottom = Stereo::new(StereoLayout::TopBottom, 0.064, 2.0);
+        assert_eq!(top_bottom.eye_size(200, 100), (200, 50));
+        assert_eq!(top_bottom.eye_pixel(150, 49, 200, 100), (-1.0, 150, 49));
+        assert_eq!(top_bottom.eye_pixel(150, 50, 200, 100), (1.0, 150, 0));
+
+        assert!(!side_by_side.splits_evenly(201, 100));
+        assert!(side_by_side.splits_evenly(200, 101));
+        assert!(!top_bottom.splits_evenly(200, 101));
+    }
+}
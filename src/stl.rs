@@ -0,0 +1,167 @@
+use crate::mesh::{invalid, MeshData, MAX_RESERVED};
+use crate::vec3::{Point3, Vec3};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Reads the STL file at `path`. See [`read`].
+pub fn load(path: &Path) -> io::Result<MeshData> {
+    read(BufReader::new(File::open(path)?))
+}
+
+/// Reads an ASCII or binary STL mesh as it streams in from `reader`. Corners that facets
+/// share are merged into one vertex, and facets wound against their stated normal are turned
+/// to follow it.
+pub fn read(mut reader: impl BufRead) -> io::Result<MeshData> {
+    // Binary headers may also start with "solid", but are not followed by facets in text.
+    let start = reader.fill_buf()?;
+    let start = &start[..start.len().min(1024)];
+    if start.starts_with(b"solid") && start.windows(5).any(|word| word == b"facet") {
+        read_ascii(reader)
+    } else {
+        read_binary(reader)
+    }
+}
+
+/// Builds indexed triangles from facets, merging corners at identical positions.
+#[derive(Default)]
+struct Builder {
+    mesh: MeshData,
+    vertices: HashMap<[u64; 3], u32>
+}
+
+impl Builder {
+    fn vertex(&mut self, p: Point3) -> u32 {
+        // Adding zero turns -0 into 0, so that the two meet.
+        let key = [p.x, p.y, p.z].map(|component| (component + 0.0).to_bits());
+        let positions = &mut self.mesh.positions;
+        *self.vertices.entry(key).or_insert_with(|| {
+            positions.push(p);
+            positions.len() as u32 - 1
+        })
+    }
+
+    /// Adds a facet, split into a fan of triangles if it has more than three corners.
+    fn facet(&mut self, normal: Vec3, corners: &[Point3]) {
+        if corners.len() < 3 {
+            return;
+        }
+        let winding = Vec3::cross(corners[1] - corners[0], corners[2] - corners[0]);
+        let flip = Vec3::dot(winding, normal) < 0.0;
+        let first = self.vertex(corners[0]);
+        for pair in corners[1..].windows(2) {
+            let (b, c) = (self.vertex(pair[0]), self.vertex(pair[1]));
+            self.mesh.triangles.push(if flip { [first, c, b] } else { [first, b, c] });
+        }
+    }
+}
+
+fn read_binary(mut reader: impl BufRead) -> io::Result<MeshData> {
+    let mut header = [0u8; 84];
+    reader.read_exact(&mut header)?;
+    let count = u32::from_le_bytes([header[80], header[81], header[82], header[83]]) as usize;
+
+    let mut builder = Builder::default();
+    builder.mesh.triangles.reserve(count.min(MAX_RESERVED));
+    // Closed meshes have about half as many vertices as facets.
+    builder.vertices.reserve(count.min(MAX_RESERVED) / 2);
+    let mut record = [0u8; 50];
+    for _ in 0..count {
+        reader.read_exact(&mut record)?;
+        let vector = |index: usize| {
+            let component = |offset: usize| {
+                let start = 12 * index + 4 * offset;
+                f32::from_le_bytes([record[start], record[start + 1], record[start + 2], record[start + 3]]) as f64
+            };
+            Vec3::new(component(0), component(1), component(2))
+        };
+        builder.facet(vector(0), &[vector(1), vector(2), vector(3)]);
+    }
+    Ok(builder.mesh)
+}
+
+fn read_ascii(reader: impl BufRead) -> io::Result<MeshData> {
+    let mut builder = Builder::default();
+    let mut normal = Vec3::zeroes();
+    let mut corners = vec![];
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = index + 1;
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("facet") => {
+                if words.next() != Some("normal") {
+                    return Err(invalid(format!("line {}: expected facet normal", number)));
+                }
+                normal = vector(&mut words, number)?;
+                corners.clear();
+            }
+            Some("vertex") => corners.push(vector(&mut words, number)?),
+            Some("endfacet") => builder.facet(normal, &corners),
+            Some("solid" | "outer" | "endloop" | "endsolid") | None => {}
+            Some(word) => return Err(invalid(format!("line {}: unexpected {}", number, word)))
+        }
+    }
+    Ok(builder.mesh)
+}
+
+/// The three numbers that follow a keyword on line `number`.
+fn vector<'a>(words: &mut impl Iterator<Item = &'a str>, number: usize) -> io::Result<Vec3> {
+    let mut component = || {
+        let word = words.next().ok_or_else(|| invalid(format!("line {}: expected three numbers", number)))?;
+        word.parse::<f64>().map_err(|_| invalid(format!("line {}: invalid number {}", number, word)))
+    };
+    Ok(Vec3::new(component()?, component()?, component()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = "solid square
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex -0 1 0
+      vertex 1 1 0
+    endloop
+  endfacet
+endsolid square
+";
+
+    #[test]
+    fn reads_ascii_and_merges_corners() {
+        let mesh = read(ASCII.as_bytes()).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        // The second facet is wound clockwise, so it is turned to face its normal.
+        assert_eq!(mesh.triangles, [[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn reads_binary() {
+        // A header that starts like an ASCII file, as some exporters write.
+        let mut data = b"solid exported".to_vec();
+        data.resize(80, 0);
+        data.extend(2u32.to_le_bytes());
+        for facet in [[[0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]], [[0.0; 3], [0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]] {
+            for value in facet.iter().flatten() {
+                data.extend((*value as f32).to_le_bytes());
+            }
+            data.extend([0, 0]);
+        }
+
+        let mesh = read(data.as_slice()).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(read(&data[..data.len() - 10]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
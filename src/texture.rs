@@ -0,0 +1,67 @@
+use crate::color::Color;
+use crate::vec3::Point3;
+use std::sync::Arc;
+
+/// Color that varies over a surface.
+pub trait Texture: Send + Sync {
+    /// Color at surface coordinates (`u`, `v`) and world position `p`.
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+}
+
+pub struct SolidColor {
+    albedo: Color
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> SolidColor {
+        SolidColor { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// A 3D checkerboard of cubes `scale` wide, alternating between two textures.
+pub struct Checker {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>
+}
+
+impl Checker {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Checker {
+        Checker { inv_scale: 1.0 / scale, even, odd }
+    }
+
+    pub fn from_colors(scale: f64, even: Color, odd: Color) -> Checker {
+        Checker::new(scale, Arc::new(SolidColor::new(even)), Arc::new(SolidColor::new(odd)))
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let cell = |x: f64| (self.inv_scale * x).floor() as i64;
+        if (cell(p.x) + cell(p.y) + cell(p.z)).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checker_alternates() {
+        let checker = Checker::from_colors(0.5, Color::zeroes(), Color::ones());
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(0.1, 0.1, 0.1)), Color::zeroes());
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(0.6, 0.1, 0.1)), Color::ones());
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(-0.1, 0.1, 0.1)), Color::ones());
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(-0.6, 0.1, 0.1)), Color::zeroes());
+    }
+}
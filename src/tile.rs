@@ -0,0 +1,179 @@
+/// A rectangle of pixels rendered as one unit of work, spanning `x0..x1` and `y0..y1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize
+}
+
+impl Tile {
+    pub fn width(&self) -> usize {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> usize {
+        self.y1 - self.y0
+    }
+
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.y0..self.y1).flat_map(move |j| (self.x0..self.x1).map(move |i| (i, j)))
+    }
+
+    pub fn contains(&self, i: usize, j: usize) -> bool {
+        (self.x0..self.x1).contains(&i) && (self.y0..self.y1).contains(&j)
+    }
+
+    /// The pixels this tile shares with `other`, if any.
+    pub fn intersect(&self, other: &Tile) -> Option<Tile> {
+        let tile = Tile {
+            x0: usize::max(self.x0, other.x0),
+            y0: usize::max(self.y0, other.y0),
+            x1: usize::min(self.x1, other.x1),
+            y1: usize::min(self.y1, other.y1)
+        };
+        (tile.x0 < tile.x1 && tile.y0 < tile.y1).then_some(tile)
+    }
+}
+
+/// Order in which tiles are handed to the render threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    Scanline,
+    /// Outwards from the center of the image, so the subject usually resolves first.
+    #[default]
+    Spiral,
+    /// Along a Hilbert curve, keeping consecutive tiles close together for cache locality.
+    Hilbert
+}
+
+/// Splits a `width` by `height` image into square tiles of `size` pixels, clipped at the
+/// right and bottom edges, in the given order.
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+
+    let tile = |(tx, ty): (usize, usize)| Tile {
+        x0: tx * size,
+        y0: ty * size,
+        x1: usize::min((tx + 1) * size, width),
+        y1: usize::min((ty + 1) * size, height)
+    };
+
+    let coords: Vec<(usize, usize)> = match order {
+        TileOrder::Scanline => (0..rows).flat_map(|ty| (0..columns).map(move |tx| (tx, ty))).collect(),
+        TileOrder::Spiral => spiral(columns, rows),
+        TileOrder::Hilbert => hilbert(columns, rows)
+    };
+
+    coords.into_iter().map(tile).collect()
+}
+
+fn spiral(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    let total = columns * rows;
+    let mut coords = Vec::with_capacity(total);
+    let (mut x, mut y) = ((columns as isize - 1) / 2, (rows as isize - 1) / 2);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut leg = 1;
+    let mut direction = 0;
+
+    let visit = |x: isize, y: isize, coords: &mut Vec<(usize, usize)>| {
+        if 0 <= x && x < columns as isize && 0 <= y && y < rows as isize {
+            coords.push((x as usize, y as usize));
+        }
+    };
+
+    visit(x, y, &mut coords);
+    while coords.len() < total {
+        for _ in 0..2 {
+            let (dx, dy) = directions[direction];
+            for _ in 0..leg {
+                x += dx;
+                y += dy;
+                visit(x, y, &mut coords);
+            }
+            direction = (direction + 1) % 4;
+        }
+        leg += 1;
+    }
+    coords
+}
+
+fn hilbert(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    let n = usize::max(columns, rows).next_power_of_two();
+    (0..n * n)
+        .map(|d| hilbert_point(n, d))
+        .filter(|&(x, y)| x < columns && y < rows)
+        .collect()
+}
+
+/// Converts distance `d` along a Hilbert curve filling an `n` by `n` grid into grid coordinates.
+fn hilbert_point(n: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coverage(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<u32> {
+        let mut hits = vec![0; width * height];
+        for tile in tiles(width, height, size, order) {
+            for (i, j) in tile.pixels() {
+                hits[j * width + i] += 1;
+            }
+        }
+        hits
+    }
+
+    #[test]
+    fn orders_cover_every_pixel_once() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            assert!(coverage(37, 21, 8, order).iter().all(|&hits| hits == 1));
+            assert!(coverage(5, 40, 16, order).iter().all(|&hits| hits == 1));
+        }
+    }
+
+    #[test]
+    fn spiral_starts_in_center() {
+        let order = tiles(30, 30, 10, TileOrder::Spiral);
+        assert_eq!(order[0], Tile { x0: 10, y0: 10, x1: 20, y1: 20 });
+        assert_eq!(order.len(), 9);
+    }
+
+    #[test]
+    fn hilbert_steps_to_neighbors() {
+        let order = tiles(32, 32, 4, TileOrder::Hilbert);
+        for pair in order.windows(2) {
+            let distance = pair[0].x0.abs_diff(pair[1].x0) + pair[0].y0.abs_diff(pair[1].y0);
+            assert_eq!(distance, 4);
+        }
+    }
+
+    #[test]
+    fn intersect() {
+        let a = Tile { x0: 0, y0: 0, x1: 10, y1: 10 };
+        assert_eq!(a.intersect(&Tile { x0: 5, y0: 8, x1: 20, y1: 20 }), Some(Tile { x0: 5, y0: 8, x1: 10, y1: 10 }));
+        assert_eq!(a.intersect(&Tile { x0: 10, y0: 0, x1: 20, y1: 10 }), None);
+        assert!(a.contains(9, 0) && !a.contains(10, 0));
+    }
+}
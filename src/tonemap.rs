@@ -0,0 +1,156 @@
+use crate::color::{Color, luminance};
+use crate::film::Film;
+use crate::mat3::Mat3;
+
+/// Curve mapping scene-referred linear color into the displayable [0, 1] range.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Tonemapper {
+    /// No curve; values above one are clipped when quantized.
+    #[default]
+    Clamp,
+    /// Extended Reinhard on luminance, mapping `white` to one.
+    Reinhard { white: f64 },
+    /// John Hable's filmic curve from Uncharted 2.
+    Hable,
+    /// Stephen Hill's fit of the ACES reference rendering and sRGB output transforms.
+    Aces,
+    /// Troy Sobotka's AgX with the default contrast look, via a polynomial fit.
+    AgX
+}
+
+/// Exposure and tonemapping applied before a film is quantized for display.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ToneMapping {
+    /// Exposure adjustment in stops.
+    pub exposure: f64,
+    pub tonemapper: Tonemapper
+}
+
+impl ToneMapping {
+    pub fn new(exposure: f64, tonemapper: Tonemapper) -> ToneMapping {
+        ToneMapping { exposure, tonemapper }
+    }
+
+    /// Maps one linear color to display-linear color.
+    pub fn apply(&self, color: Color) -> Color {
+        let color = 2f64.powf(self.exposure) * color;
+        match self.tonemapper {
+            Tonemapper::Clamp => color,
+            Tonemapper::Reinhard { white } => reinhard(color, white),
+            Tonemapper::Hable => hable(color),
+            Tonemapper::Aces => aces(color),
+            Tonemapper::AgX => agx(color)
+        }
+    }
+
+    pub fn apply_film(&self, film: &Film) -> Film {
+        film.map(|color| self.apply(color))
+    }
+}
+
+fn reinhard(color: Color, white: f64) -> Color {
+    let l = luminance(color);
+    if l <= 0.0 {
+        return Color::zeroes();
+    }
+    let mapped = l * (1.0 + l / (white * white)) / (1.0 + l);
+    (mapped / l) * color
+}
+
+fn hable(color: Color) -> Color {
+    const EXPOSURE_BIAS: f64 = 2.0;
+    const WHITE: f64 = 11.2;
+    let curve = |x: f64| {
+        const A: f64 = 0.15;
+        const B: f64 = 0.50;
+        const C: f64 = 0.10;
+        const D: f64 = 0.20;
+        const E: f64 = 0.02;
+        const F: f64 = 0.30;
+        ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+    };
+    let white_scale = 1.0 / curve(WHITE);
+    map_components(color, |x| curve(EXPOSURE_BIAS * x.max(0.0)) * white_scale)
+}
+
+fn aces(color: Color) -> Color {
+    const INPUT: Mat3 = Mat3::new([
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777]
+    ]);
+    const OUTPUT: Mat3 = Mat3::new([
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602]
+    ]);
+    let fitted = map_components(INPUT * color, |v| {
+        (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.4329510) + 0.238081)
+    });
+    map_components(OUTPUT * fitted, |v| v.clamp(0.0, 1.0))
+}
+
+fn agx(color: Color) -> Color {
+    const INSET: Mat3 = Mat3::new([
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104]
+    ]);
+    const OUTSET: Mat3 = Mat3::new([
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116]
+    ]);
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    let encoded = map_components(INSET * color, |v| {
+        let x = (v.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+    });
+    map_components(OUTSET * encoded, |v| v.max(0.0).powf(2.2))
+}
+
+fn map_components(color: Color, f: impl Fn(f64) -> f64) -> Color {
+    Color::new(f(color.x), f(color.y), f(color.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(value: f64) -> Color {
+        Color::new(value, value, value)
+    }
+
+    #[test]
+    fn exposure() {
+        let tone_mapping = ToneMapping::new(1.0, Tonemapper::Clamp);
+        assert_eq!(tone_mapping.apply(gray(0.25)), gray(0.5));
+    }
+
+    #[test]
+    fn reinhard_maps_white_to_one() {
+        let tone_mapping = ToneMapping::new(0.0, Tonemapper::Reinhard { white: 4.0 });
+        let mapped = tone_mapping.apply(gray(4.0));
+        assert!((mapped.x - 1.0).abs() < 1e-12);
+        assert_eq!(tone_mapping.apply(Color::zeroes()), Color::zeroes());
+    }
+
+    #[test]
+    fn curves_are_monotonic_and_bounded() {
+        for tonemapper in [Tonemapper::Hable, Tonemapper::Aces, Tonemapper::AgX, Tonemapper::Reinhard { white: 8.0 }] {
+            let tone_mapping = ToneMapping::new(0.0, tonemapper);
+            let mut previous = -1.0;
+            for step in 0..200 {
+                let value = tone_mapping.apply(gray(step as f64 * 0.025)).y;
+                assert!(value >= previous, "{:?} is not monotonic", tonemapper);
+                assert!(value <= 1.01, "{:?} exceeds one", tonemapper);
+                previous = value;
+            }
+            assert!(tone_mapping.apply(gray(0.0)).y < 0.01, "{:?} lifts black", tonemapper);
+        }
+    }
+}
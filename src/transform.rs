@@ -0,0 +1,136 @@
+use crate::error::{Error, Result};
+use crate::hittable::{Hittable, HitRecord};
+use crate::mat3::Mat3;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Affine transform: a linear map followed by a translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub linear: Mat3,
+    pub translation: Vec3
+}
+
+impl Transform {
+    pub const fn identity() -> Transform {
+        Transform { linear: Mat3::identity(), translation: Vec3::new(0.0, 0.0, 0.0) }
+    }
+
+    pub const fn translate(offset: Vec3) -> Transform {
+        Transform { linear: Mat3::identity(), translation: offset }
+    }
+
+    pub const fn scale(factors: Vec3) -> Transform {
+        Transform { linear: Mat3::diagonal(factors), translation: Vec3::new(0.0, 0.0, 0.0) }
+    }
+
+    /// Rotation by `degrees` counterclockwise about `axis`, looking down the axis.
+    pub fn rotate(axis: Vec3, degrees: f64) -> Transform {
+        let k = axis.unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        let linear = Mat3::new([
+            [cos + t * k.x * k.x, t * k.x * k.y - sin * k.z, t * k.x * k.z + sin * k.y],
+            [t * k.y * k.x + sin * k.z, cos + t * k.y * k.y, t * k.y * k.z - sin * k.x],
+            [t * k.z * k.x - sin * k.y, t * k.z * k.y + sin * k.x, cos + t * k.z * k.z]
+        ]);
+        Transform { linear, translation: Vec3::zeroes() }
+    }
+
+    /// This transform followed by `next`.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform { linear: next.linear * self.linear, translation: next.linear * self.translation + next.translation }
+    }
+
+    pub fn point(&self, p: Point3) -> Point3 {
+        self.linear * p + self.translation
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.linear * v
+    }
+
+    /// The inverse transform, or `None` when the linear map flattens space.
+    pub fn inverse(&self) -> Option<Transform> {
+        if self.linear.determinant().abs() < 1e-12 {
+            return None;
+        }
+        let linear = self.linear.inverse();
+        Some(Transform { linear, translation: -(linear * self.translation) })
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// An object placed in the world by a transform of its own coordinates.
+pub struct Instance {
+    object: Box<dyn Hittable>,
+    transform: Transform,
+    inverse: Transform,
+    /// Maps object-space normals to world space.
+    normal_matrix: Mat3
+}
+
+impl Instance {
+    pub fn new(object: Box<dyn Hittable>, transform: Transform) -> Instance {
+        Self::try_new(object, transform).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(object: Box<dyn Hittable>, transform: Transform) -> Result<Instance> {
+        let inverse = transform.inverse().ok_or(Error::invalid_value("Instance transform", "must be invertible"))?;
+        Ok(Instance { object, transform, inverse, normal_matrix: inverse.linear.transpose() })
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, bounds: (f64, f64)) -> Option<HitRecord> {
+        // The ray keeps its parameterization in object space, so hit distances carry over.
+        let local = Ray::new(self.inverse.point(r.origin), self.inverse.vector(r.direction));
+        let rec = self.object.hit(&local, bounds)?;
+        Some(HitRecord {
+            p: self.transform.point(rec.p),
+            normal: (self.normal_matrix * rec.normal).unit(),
+            ..rec
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::geometry::Sphere;
+    use crate::material::Lambertian;
+    use std::sync::Arc;
+
+    #[test]
+    fn composes_and_inverts() {
+        let transform = Transform::scale(Vec3::new(2.0, 2.0, 2.0))
+            .then(&Transform::rotate(Vec3::new(0.0, 0.0, 1.0), 90.0))
+            .then(&Transform::translate(Vec3::new(1.0, 0.0, 0.0)));
+        let p = transform.point(Point3::new(1.0, 0.0, 0.0));
+        assert!((p - Point3::new(1.0, 2.0, 0.0)).length() < 1e-12);
+        let back = transform.inverse().unwrap().point(p);
+        assert!((back - Point3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        assert!(Transform::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn stretched_sphere() {
+        let sphere = Sphere::new(Point3::zeroes(), 1.0, Arc::new(Lambertian::new(Color::ones())));
+        let transform = Transform::scale(Vec3::new(1.0, 3.0, 1.0)).then(&Transform::translate(Vec3::new(0.0, 0.0, -5.0)));
+        let instance = Instance::new(Box::new(sphere), transform);
+
+        let rec = instance.hit(&Ray::new(Point3::new(0.0, 10.0, -5.0), Vec3::new(0.0, -1.0, 0.0)), (0.001, f64::INFINITY)).unwrap();
+        assert!((rec.p - Point3::new(0.0, 3.0, -5.0)).length() < 1e-9);
+        assert!((rec.t - 7.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+
+        let side = instance.hit(&Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), (0.001, f64::INFINITY)).unwrap();
+        assert!((side.p - Point3::new(0.0, 0.0, -4.0)).length() < 1e-9);
+    }
+}
@@ -1,17 +1,62 @@
 use rand::Rng;
+use std::cell::Cell;
+
+thread_local! {
+    /// SplitMix64 state of the current thread, randomly seeded until `seed_random` is called.
+    static RANDOM_STATE: Cell<u64> = Cell::new(rand::thread_rng().gen());
+}
 
 pub fn random_double() -> f64 {
-    rand::thread_rng().gen()
+    RANDOM_STATE.with(|state| {
+        let next = state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state.set(next);
+        (mix64(next) >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    })
 }
 
 pub fn random_range(min: f64, max: f64) -> f64 {
     min + (max - min) * random_double()
 }
 
+/// Restarts the current thread's random sequence from `seed`, so that whatever is drawn next
+/// repeats exactly for the same seed.
+pub fn seed_random(seed: u64) {
+    RANDOM_STATE.with(|state| state.set(seed));
+}
+
+/// Hashes `values` into one well-mixed seed.
+pub fn hash_seed(values: &[u64]) -> u64 {
+    values.iter().fold(0, |hash, &value| mix64(hash ^ mix64(value.wrapping_add(0x9e37_79b9_7f4a_7c15))))
+}
+
+/// The SplitMix64 finalizer.
+fn mix64(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 pub fn clamp(num: f64, (min, max): (f64, f64)) -> f64 {
     match num {
         _ if num < min => min,
         _ if num > max => max,
         _ => num
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_sequences_repeat() {
+        seed_random(hash_seed(&[1, 2, 3]));
+        let first: Vec<f64> = (0..4).map(|_| random_double()).collect();
+        seed_random(hash_seed(&[1, 2, 3]));
+        let second: Vec<f64> = (0..4).map(|_| random_double()).collect();
+        assert_eq!(first, second);
+        assert!(first.iter().all(|&value| (0.0..1.0).contains(&value)));
+        assert_ne!(hash_seed(&[1, 2, 3]), hash_seed(&[1, 3, 2]));
+    }
+}
@@ -1,5 +1,4 @@
 use std::fmt;
-use assert_float_eq::assert_float_absolute_eq;
 use std::ops::{Neg, Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign, Index, IndexMut};
 use crate::utils::{random_double, random_range}; 
 
@@ -244,18 +243,18 @@ impl DivAssign<f64> for Vec3 {
     }
 }
 
-macro_rules! assert_vec3_eq {
-    ($x:expr, $y:expr) => {
-        assert_float_absolute_eq!($x.x, $y.x);
-        assert_float_absolute_eq!($x.y, $y.y);
-        assert_float_absolute_eq!($x.z, $y.z);
-    }
-}
-
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use assert_float_eq::assert_float_absolute_eq;
+
+    macro_rules! assert_vec3_eq {
+        ($x:expr, $y:expr) => {
+            assert_float_absolute_eq!($x.x, $y.x);
+            assert_float_absolute_eq!($x.y, $y.y);
+            assert_float_absolute_eq!($x.z, $y.z);
+        }
+    }
 
     #[test]
     fn length() {